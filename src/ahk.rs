@@ -0,0 +1,518 @@
+//! AutoHotkey `Send` syntax.
+//!
+//! Supported:
+//! - Modifiers `^` (Ctrl), `+` (Shift), `!` (Alt) and `#` (Win) that apply to the next key or character
+//! - Keys in braces `{Enter}`, repeated up to 1000 times `{Tab 3}`, held `{Shift down}` and released `{Shift up}`
+//! - Escaped symbols `{{}`, `{}}`, `{^}`, `{+}`, `{!}` and `{#}`
+//! - Unicode characters `{U+00E9}` and virtual key codes `{vk41}`
+//! - `{Raw}` and `{Text}` which send the rest of the string literally
+//! - `{Blind}` which is accepted and ignored, modifiers held by the user are never released
+//!
+//! Plain characters are typed as unicode characters so they do not depend on the keyboard layout.
+//! Letters and digits with a modifier, or while a modifier is held with `{Shift down}` and the like,
+//! are sent as keys so shortcuts like `^c` work.
+//! `\n` and `\t` send Enter and Tab.
+//! ```no_run
+//! # use kemuler_windows::{Windows, ahk::SendKeys};
+//! SendKeys::parse("!{F4}").unwrap().run(&mut Windows::new());
+//! ```
+
+use std::{error::Error, fmt, str::FromStr};
+
+use kemuler::{common_inputs, input_event::SetTo, simulator::Simulate};
+
+use crate::{
+    hotkey::Modifier,
+    input_macro::{Macro, MacroEvent},
+    VirtualKey,
+};
+
+/// AutoHotkey key names and the keys they press, names are matched ignoring case
+pub const KEY_NAMES: &[(&str, VirtualKey)] = &[
+    ("Enter", VirtualKey::Enter),
+    ("Return", VirtualKey::Enter),
+    ("Escape", VirtualKey::Escape),
+    ("Esc", VirtualKey::Escape),
+    ("Space", VirtualKey::Space),
+    ("Tab", VirtualKey::Tab),
+    ("Backspace", VirtualKey::Backspace),
+    ("BS", VirtualKey::Backspace),
+    ("Delete", VirtualKey::Delete),
+    ("Del", VirtualKey::Delete),
+    ("Insert", VirtualKey::Insert),
+    ("Ins", VirtualKey::Insert),
+    ("Up", VirtualKey::UpArrow),
+    ("Down", VirtualKey::DownArrow),
+    ("Left", VirtualKey::LeftArrow),
+    ("Right", VirtualKey::RightArrow),
+    ("Home", VirtualKey::Home),
+    ("End", VirtualKey::End),
+    ("PgUp", VirtualKey::PageUp),
+    ("PgDn", VirtualKey::PageDown),
+    ("CapsLock", VirtualKey::CapsLock),
+    ("ScrollLock", VirtualKey::ScrollLock),
+    ("NumLock", VirtualKey::NumLock),
+    ("Control", VirtualKey::Control),
+    ("Ctrl", VirtualKey::Control),
+    ("LControl", VirtualKey::LControl),
+    ("LCtrl", VirtualKey::LControl),
+    ("RControl", VirtualKey::RControl),
+    ("RCtrl", VirtualKey::RControl),
+    ("Alt", VirtualKey::Alt),
+    ("LAlt", VirtualKey::LAlt),
+    ("RAlt", VirtualKey::RAlt),
+    ("Shift", VirtualKey::Shift),
+    ("LShift", VirtualKey::LShift),
+    ("RShift", VirtualKey::RShift),
+    ("LWin", VirtualKey::LWin),
+    ("RWin", VirtualKey::RWin),
+    ("AppsKey", VirtualKey::Apps),
+    ("Sleep", VirtualKey::Sleep),
+    ("PrintScreen", VirtualKey::PrintScreen),
+    ("Pause", VirtualKey::Pause),
+    ("CtrlBreak", VirtualKey::Cancel),
+    ("Help", VirtualKey::Help),
+    ("F1", VirtualKey::F1),
+    ("F2", VirtualKey::F2),
+    ("F3", VirtualKey::F3),
+    ("F4", VirtualKey::F4),
+    ("F5", VirtualKey::F5),
+    ("F6", VirtualKey::F6),
+    ("F7", VirtualKey::F7),
+    ("F8", VirtualKey::F8),
+    ("F9", VirtualKey::F9),
+    ("F10", VirtualKey::F10),
+    ("F11", VirtualKey::F11),
+    ("F12", VirtualKey::F12),
+    ("F13", VirtualKey::F13),
+    ("F14", VirtualKey::F14),
+    ("F15", VirtualKey::F15),
+    ("F16", VirtualKey::F16),
+    ("F17", VirtualKey::F17),
+    ("F18", VirtualKey::F18),
+    ("F19", VirtualKey::F19),
+    ("F20", VirtualKey::F20),
+    ("F21", VirtualKey::F21),
+    ("F22", VirtualKey::F22),
+    ("F23", VirtualKey::F23),
+    ("F24", VirtualKey::F24),
+    ("Numpad0", VirtualKey::NumPad0),
+    ("Numpad1", VirtualKey::NumPad1),
+    ("Numpad2", VirtualKey::NumPad2),
+    ("Numpad3", VirtualKey::NumPad3),
+    ("Numpad4", VirtualKey::NumPad4),
+    ("Numpad5", VirtualKey::NumPad5),
+    ("Numpad6", VirtualKey::NumPad6),
+    ("Numpad7", VirtualKey::NumPad7),
+    ("Numpad8", VirtualKey::NumPad8),
+    ("Numpad9", VirtualKey::NumPad9),
+    ("NumpadDot", VirtualKey::Decimal),
+    ("NumpadDiv", VirtualKey::Divide),
+    ("NumpadMult", VirtualKey::Multiply),
+    ("NumpadAdd", VirtualKey::Add),
+    ("NumpadSub", VirtualKey::Subtract),
+    ("NumpadEnter", VirtualKey::Enter),
+    ("NumpadIns", VirtualKey::Insert),
+    ("NumpadEnd", VirtualKey::End),
+    ("NumpadDown", VirtualKey::DownArrow),
+    ("NumpadPgDn", VirtualKey::PageDown),
+    ("NumpadLeft", VirtualKey::LeftArrow),
+    ("NumpadClear", VirtualKey::Clear),
+    ("NumpadRight", VirtualKey::RightArrow),
+    ("NumpadHome", VirtualKey::Home),
+    ("NumpadUp", VirtualKey::UpArrow),
+    ("NumpadPgUp", VirtualKey::PageUp),
+    ("NumpadDel", VirtualKey::Delete),
+    ("Browser_Back", VirtualKey::BrowserBack),
+    ("Browser_Forward", VirtualKey::BrowserForward),
+    ("Browser_Refresh", VirtualKey::BrowserRefresh),
+    ("Browser_Stop", VirtualKey::BrowserStop),
+    ("Browser_Search", VirtualKey::BrowserSearch),
+    ("Browser_Favorites", VirtualKey::BrowserFavorites),
+    ("Browser_Home", VirtualKey::BrowserHome),
+    ("Volume_Mute", VirtualKey::VolumeMute),
+    ("Volume_Down", VirtualKey::VolumeDown),
+    ("Volume_Up", VirtualKey::VolumeUp),
+    ("Media_Next", VirtualKey::MediaNextTrack),
+    ("Media_Prev", VirtualKey::MediaPrevTrack),
+    ("Media_Stop", VirtualKey::MediaStop),
+    ("Media_Play_Pause", VirtualKey::MediaPlayPause),
+    ("Launch_Mail", VirtualKey::LaunchMail),
+    ("Launch_Media", VirtualKey::LaunchMediaSelect),
+    ("Launch_App1", VirtualKey::LaunchApp1),
+    ("Launch_App2", VirtualKey::LaunchApp2),
+];
+
+/// Find the key for an AutoHotkey key name.
+/// Besides [`KEY_NAMES`], letters, digits and `vkXX` codes are accepted.
+/// ```
+/// # use kemuler_windows::{VirtualKey, ahk::key_from_name};
+/// assert_eq!(key_from_name("pgdn"), Some(VirtualKey::PageDown));
+/// assert_eq!(key_from_name("a"), Some(VirtualKey::A));
+/// assert_eq!(key_from_name("vk41"), Some(VirtualKey::A));
+/// assert_eq!(key_from_name("Numpad"), None);
+/// ```
+pub fn key_from_name(name: &str) -> Option<VirtualKey> {
+    if let Some((_, key)) = KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+    {
+        return Some(*key);
+    }
+    if let Some(code) = name.strip_prefix("vk").or_else(|| name.strip_prefix("VK")) {
+        return u16::from_str_radix(code, 16)
+            .ok()
+            .and_then(VirtualKey::from_code);
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => VirtualKey::from_alphanumeric(char),
+        _ => None,
+    }
+}
+
+/// A key or character event produced by [`SendKeys`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SendEvent {
+    Key { key: VirtualKey, is_down: bool },
+    Char { char: char, is_down: bool },
+}
+
+impl SendEvent {
+    /// Simulate the event on `simulator`
+    pub fn simulate_with<S>(self, simulator: &mut S)
+    where
+        S: Simulate<SetTo<VirtualKey, bool>> + Simulate<SetTo<common_inputs::Char, bool>>,
+    {
+        match self {
+            SendEvent::Key { key, is_down } => simulator.simulate(SetTo::new(key, is_down)),
+            SendEvent::Char { char, is_down } => {
+                simulator.simulate(SetTo::new(common_inputs::Char(char), is_down))
+            }
+        }
+    }
+}
+
+impl From<SendEvent> for MacroEvent {
+    fn from(event: SendEvent) -> Self {
+        match event {
+            SendEvent::Key { key, is_down } => MacroEvent::Key { key, is_down },
+            SendEvent::Char { char, is_down } => MacroEvent::Char { char, is_down },
+        }
+    }
+}
+
+/// What is wrong in a `Send` string
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SendParseErrorKind {
+    UnterminatedBrace,
+    UnknownKey(String),
+    /// The word after a key name is not `down`, `up` or a count up to [`SendKeys::MAX_REPEAT`]
+    InvalidArgument(String),
+    InvalidUnicode(String),
+    /// A modifier symbol at the end of the string
+    DanglingModifier,
+}
+
+impl fmt::Display for SendParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendParseErrorKind::UnterminatedBrace => write!(f, "unterminated `{{`"),
+            SendParseErrorKind::UnknownKey(name) => write!(f, "unknown key `{name}`"),
+            SendParseErrorKind::InvalidArgument(argument) => write!(
+                f,
+                "invalid argument `{argument}`, expected `down`, `up` or a count up to {}",
+                SendKeys::MAX_REPEAT
+            ),
+            SendParseErrorKind::InvalidUnicode(code) => {
+                write!(f, "invalid unicode character `U+{code}`")
+            }
+            SendParseErrorKind::DanglingModifier => write!(f, "modifier without a key"),
+        }
+    }
+}
+
+/// Error returned by [`SendKeys::parse`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SendParseError {
+    /// Column in characters starting at 1
+    pub column: usize,
+    pub kind: SendParseErrorKind,
+}
+
+impl fmt::Display for SendParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.kind)
+    }
+}
+
+impl Error for SendParseError {}
+
+/// A parsed AutoHotkey `Send` string
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct SendKeys {
+    pub events: Vec<SendEvent>,
+}
+
+impl SendKeys {
+    /// Most presses of one repeated key like `{Tab 3}`
+    pub const MAX_REPEAT: usize = 1000;
+
+    /// Parse a `Send` string.
+    /// ```
+    /// # use kemuler_windows::{VirtualKey, ahk::*};
+    /// let key = |key, is_down| SendEvent::Key { key, is_down };
+    /// assert_eq!(SendKeys::parse("^c").unwrap().events, [
+    ///     key(VirtualKey::Control, true),
+    ///     key(VirtualKey::C, true),
+    ///     key(VirtualKey::C, false),
+    ///     key(VirtualKey::Control, false),
+    /// ]);
+    /// assert_eq!(SendKeys::parse("+{Tab 2}").unwrap().events, [
+    ///     key(VirtualKey::Shift, true),
+    ///     key(VirtualKey::Tab, true),
+    ///     key(VirtualKey::Tab, false),
+    ///     key(VirtualKey::Tab, true),
+    ///     key(VirtualKey::Tab, false),
+    ///     key(VirtualKey::Shift, false),
+    /// ]);
+    /// assert_eq!(SendKeys::parse("{Shift down}a{Shift up}a{Raw}{").unwrap().events, [
+    ///     key(VirtualKey::Shift, true),
+    ///     key(VirtualKey::A, true),
+    ///     key(VirtualKey::A, false),
+    ///     key(VirtualKey::Shift, false),
+    ///     SendEvent::Char { char: 'a', is_down: true },
+    ///     SendEvent::Char { char: 'a', is_down: false },
+    ///     SendEvent::Char { char: '{', is_down: true },
+    ///     SendEvent::Char { char: '{', is_down: false },
+    /// ]);
+    ///
+    /// let error = SendKeys::parse("ab{Entr}").unwrap_err();
+    /// assert_eq!(error.to_string(), "column 3: unknown key `Entr`");
+    ///
+    /// let error = SendKeys::parse("{Tab 4294967295}").unwrap_err();
+    /// assert_eq!(error.kind, SendParseErrorKind::InvalidArgument("4294967295".to_string()));
+    /// ```
+    pub fn parse(text: &str) -> Result<SendKeys, SendParseError> {
+        let mut events = Vec::new();
+        let mut modifiers: Vec<VirtualKey> = Vec::new();
+        let mut modifier_column = 0;
+        // modifiers pressed with `{Shift down}` and the like, until their `up`
+        let mut held_modifiers: Vec<VirtualKey> = Vec::new();
+        let mut is_raw = false;
+        let mut chars = text.chars().enumerate().peekable();
+        while let Some((index, char)) = chars.next() {
+            let column = index + 1;
+            let error = |kind| SendParseError { column, kind };
+            if is_raw {
+                push_char(&mut events, char);
+                continue;
+            }
+            let modifier = match char {
+                '^' => Some(VirtualKey::Control),
+                '+' => Some(VirtualKey::Shift),
+                '!' => Some(VirtualKey::Alt),
+                '#' => Some(VirtualKey::LWin),
+                _ => None,
+            };
+            if let Some(modifier) = modifier {
+                if modifiers.is_empty() {
+                    modifier_column = column;
+                }
+                modifiers.push(modifier);
+                continue;
+            }
+
+            let mut presses = Vec::new();
+            if char == '{' {
+                // `{}}` is the only key name containing `}`
+                let mut name = String::new();
+                if let Some(&(_, '}')) = chars.peek() {
+                    name.push('}');
+                    chars.next();
+                }
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) => break,
+                        Some((_, char)) => name.push(char),
+                        None => return Err(error(SendParseErrorKind::UnterminatedBrace)),
+                    }
+                }
+                let (name, argument) = match name.split_once(' ') {
+                    Some((name, argument)) => (name, argument.trim()),
+                    None => (name.as_str(), ""),
+                };
+                if argument.is_empty() {
+                    if name.eq_ignore_ascii_case("raw") || name.eq_ignore_ascii_case("text") {
+                        is_raw = true;
+                        continue;
+                    }
+                    if name.eq_ignore_ascii_case("blind") {
+                        continue;
+                    }
+                }
+
+                let press = if let Some(code) =
+                    name.strip_prefix("U+").or_else(|| name.strip_prefix("u+"))
+                {
+                    let char = u32::from_str_radix(code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| {
+                            error(SendParseErrorKind::InvalidUnicode(code.to_string()))
+                        })?;
+                    Press::Char(char)
+                } else {
+                    let mut name_chars = name.chars();
+                    match (name_chars.next(), name_chars.next()) {
+                        (Some(char), None) if VirtualKey::from_alphanumeric(char).is_none() => {
+                            Press::Char(char)
+                        }
+                        _ => Press::Key(key_from_name(name).ok_or_else(|| {
+                            error(SendParseErrorKind::UnknownKey(name.to_string()))
+                        })?),
+                    }
+                };
+
+                let argument = argument.to_ascii_lowercase();
+                match argument.as_str() {
+                    "" => presses.push((press, Action::Press)),
+                    "down" | "downr" | "downtemp" => presses.push((press, Action::Down)),
+                    "up" => presses.push((press, Action::Up)),
+                    count => {
+                        let count = count
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|&count| count <= SendKeys::MAX_REPEAT)
+                            .ok_or_else(|| {
+                                error(SendParseErrorKind::InvalidArgument(argument.clone()))
+                            })?;
+                        presses.extend((0..count).map(|_| (press, Action::Press)));
+                    }
+                }
+            } else {
+                presses.push((Press::Char(char), Action::Press));
+            }
+
+            let has_modifiers = !modifiers.is_empty() || !held_modifiers.is_empty();
+            events.extend(
+                modifiers
+                    .iter()
+                    .map(|&key| SendEvent::Key { key, is_down: true }),
+            );
+            for (press, action) in presses {
+                let press = match press {
+                    Press::Char(char) if has_modifiers => {
+                        VirtualKey::from_alphanumeric(char).map_or(press, Press::Key)
+                    }
+                    press => press,
+                };
+                match (press, action) {
+                    (Press::Char(char), Action::Press) => push_char(&mut events, char),
+                    (Press::Char(char), action) => events.push(SendEvent::Char {
+                        char,
+                        is_down: action == Action::Down,
+                    }),
+                    (Press::Key(key), Action::Press) => events.extend([
+                        SendEvent::Key { key, is_down: true },
+                        SendEvent::Key {
+                            key,
+                            is_down: false,
+                        },
+                    ]),
+                    (Press::Key(key), action) => {
+                        let is_down = action == Action::Down;
+                        if Modifier::from_virtual_key(key).is_some() {
+                            held_modifiers.retain(|&held| held != key);
+                            if is_down {
+                                held_modifiers.push(key);
+                            }
+                        }
+                        events.push(SendEvent::Key { key, is_down });
+                    }
+                }
+            }
+            events.extend(modifiers.drain(..).rev().map(|key| SendEvent::Key {
+                key,
+                is_down: false,
+            }));
+        }
+        if !modifiers.is_empty() {
+            return Err(SendParseError {
+                column: modifier_column,
+                kind: SendParseErrorKind::DanglingModifier,
+            });
+        }
+        Ok(SendKeys { events })
+    }
+
+    /// Every event one after another without delay
+    pub fn to_macro(&self) -> Macro {
+        let mut recorded = Macro::new();
+        for &event in &self.events {
+            recorded.push(event.into());
+        }
+        recorded
+    }
+
+    /// Simulate every event in order
+    pub fn run<S>(&self, simulator: &mut S)
+    where
+        S: Simulate<SetTo<VirtualKey, bool>> + Simulate<SetTo<common_inputs::Char, bool>>,
+    {
+        for &event in &self.events {
+            event.simulate_with(simulator);
+        }
+    }
+}
+
+impl FromStr for SendKeys {
+    type Err = SendParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        SendKeys::parse(text)
+    }
+}
+
+/// What a single item in a `Send` string presses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Press {
+    Key(VirtualKey),
+    Char(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Press,
+    Down,
+    Up,
+}
+
+/// Type a character, newlines and tabs press Enter and Tab
+fn push_char(events: &mut Vec<SendEvent>, char: char) {
+    let key = match char {
+        '\n' => Some(VirtualKey::Enter),
+        '\t' => Some(VirtualKey::Tab),
+        '\r' => return,
+        _ => None,
+    };
+    match key {
+        Some(key) => events.extend([
+            SendEvent::Key { key, is_down: true },
+            SendEvent::Key {
+                key,
+                is_down: false,
+            },
+        ]),
+        None => events.extend([
+            SendEvent::Char {
+                char,
+                is_down: true,
+            },
+            SendEvent::Char {
+                char,
+                is_down: false,
+            },
+        ]),
+    }
+}
@@ -0,0 +1,168 @@
+//! Run timed sequences without blocking the thread.
+//!
+//! [`run`] executes [`script`](crate::script) commands on [`Windows`] and awaits
+//! [`Command::Wait`] with an [`AsyncSleep`] instead of blocking, yielding between events.
+//! Any runtime works by passing its sleep function, or [`TokioSleep`] with the `tokio` feature.
+//!
+//! Dropping the future before it completes, or an error, releases the keys and buttons
+//! the run pressed and still holds, so cancelling a task never leaves keys stuck.
+//!
+//! Only [`Command::Wait`] is awaited. Everything that paces input inside a single call still
+//! sleeps on the thread polling the future: delays of [`Timing`](crate::timing::Timing),
+//! [`Typing`](crate::cadence::Typing) cadences and the frames of [`Gesture`](crate::touch::Gesture)
+//! and [`Stroke`](crate::pen::Stroke). Use waits for delays that should not block.
+//! ```no_run
+//! # use std::time::Duration;
+//! # use kemuler_windows::{VirtualKey, Windows, asynchronous, script::{smooth_move, Command}};
+//! # async fn example() -> Result<(), kemuler_windows::Error> {
+//! let mut windows = Windows::new();
+//! let mut commands = vec![
+//!     Command::KeyDown(VirtualKey::Shift),
+//!     Command::Wait(Duration::from_millis(500)),
+//!     Command::KeyUp(VirtualKey::Shift),
+//! ];
+//! commands.extend(smooth_move((0, 0), (500, 300), Duration::from_millis(250), 25));
+//! # #[cfg(feature = "tokio")]
+//! asynchronous::run(&mut windows, commands, &asynchronous::TokioSleep).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use crate::{script::Command, timing::Held, Error, TrySimulate, Windows};
+
+/// Non-blocking sleep of an async runtime
+pub trait AsyncSleep {
+    type Sleep: Future<Output = ()>;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+}
+
+impl<F, Fut> AsyncSleep for F
+where
+    F: Fn(Duration) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    type Sleep = Fut;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        self(duration)
+    }
+}
+
+/// Sleep with [`tokio::time::sleep`]
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TokioSleep;
+
+#[cfg(feature = "tokio")]
+impl AsyncSleep for TokioSleep {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep(&self, duration: Duration) -> Self::Sleep {
+        tokio::time::sleep(duration)
+    }
+}
+
+/// Run `commands` on `windows`, awaiting waits with `sleep`.
+///
+/// Keys and buttons held down by the commands stay down when the future completes.
+/// When it is dropped early or a command fails, the ones pressed by `commands` are released,
+/// keys held before the run are left alone.
+/// ```
+/// # use std::{future::{pending, Future}, pin::pin, task::{Context, Poll, Waker}, time::Duration};
+/// # use kemuler::prelude::*;
+/// # use kemuler_windows::{VirtualKey, Windows, asynchronous, dry_run::Recording, script::Command};
+/// let log = Recording::new();
+/// let mut windows = Windows::new().with_signature(0).with_dry_run(log.clone());
+/// windows.simulate(VirtualKey::LControl.down());
+/// let commands = [
+///     Command::KeyDown(VirtualKey::LShift),
+///     Command::Wait(Duration::from_secs(60)),
+///     Command::KeyUp(VirtualKey::LShift),
+/// ];
+/// let sleep = |_| pending::<()>();
+/// {
+///     let mut future = pin!(asynchronous::run(&mut windows, commands, &sleep));
+///     let mut context = Context::from_waker(Waker::noop());
+///     for _ in 0..3 {
+///         assert!(future.as_mut().poll(&mut context).is_pending());
+///     }
+///     // cancelled while waiting
+/// }
+/// let lines = log.lines();
+/// assert_eq!(lines.len(), 3);
+/// assert!(lines[1].starts_with("KEYDOWN VK_LSHIFT"));
+/// assert!(lines[2].starts_with("KEYUP VK_LSHIFT"));
+/// ```
+pub async fn run<Sl>(
+    windows: &mut Windows,
+    commands: impl IntoIterator<Item = Command>,
+    sleep: &Sl,
+) -> Result<(), Error>
+where
+    Sl: AsyncSleep,
+{
+    let mut guard = ReleaseOnDrop {
+        windows,
+        pressed: Vec::new(),
+        is_done: false,
+    };
+    for command in commands {
+        match command {
+            Command::Wait(duration) => sleep.sleep(duration).await,
+            command => {
+                // every other command releases what it presses before returning
+                match command {
+                    Command::KeyDown(key) => guard.pressed.push(Held::Key(key.code().0)),
+                    Command::MouseDown(button) => guard.pressed.push(Held::MouseButton(button)),
+                    _ => {}
+                }
+                guard.windows.try_simulate(command)?;
+                YieldNow { has_yielded: false }.await;
+            }
+        }
+    }
+    guard.is_done = true;
+    Ok(())
+}
+
+/// Release what the sequence pressed unless it finished
+struct ReleaseOnDrop<'a> {
+    windows: &'a mut Windows,
+    pressed: Vec<Held>,
+    is_done: bool,
+}
+
+impl Drop for ReleaseOnDrop<'_> {
+    fn drop(&mut self) {
+        if !self.is_done {
+            self.windows.release(self.pressed.drain(..));
+        }
+    }
+}
+
+/// Let other tasks run once
+struct YieldNow {
+    has_yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+        if self.has_yielded {
+            Poll::Ready(())
+        } else {
+            self.has_yielded = true;
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,291 @@
+//! Human-like typing rhythm.
+//!
+//! [`Cadence`] turns text into a schedule of [`Keystroke`]s with realistic delays:
+//! every key takes a slightly different time, common letter pairs are typed faster,
+//! there are pauses between words and after punctuation,
+//! and typos can be made and corrected with backspace.
+//! ```no_run
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{Windows, cadence::*};
+//! let cadence = Cadence::new().with_typo_rate(0.03);
+//! Windows::new().simulate(cadence.typing("Hello, world!"));
+//! ```
+//!
+//! Schedules are random, set a seed to make them reproducible.
+//! ```
+//! # use kemuler_windows::cadence::*;
+//! let cadence = Cadence::new().with_seed(42).with_typo_rate(0.1);
+//! assert_eq!(cadence.schedule("the quick brown fox"), cadence.schedule("the quick brown fox"));
+//! ```
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Letter pairs that are typed faster than others, in lowercase
+pub const FAST_DIGRAPHS: &[&str] = &[
+    "th", "he", "in", "er", "an", "re", "on", "at", "en", "nd", "ti", "es", "or", "te", "of", "ed",
+    "is", "it", "al", "ar", "st", "to", "nt", "ng", "se", "ha", "as", "ou", "io", "le",
+];
+
+/// Keyboard rows used to pick a neighbouring key for a typo
+const KEYBOARD_ROWS: &[&str] = &["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// A key typed by [`Cadence`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypedKey {
+    Char(char),
+    /// Delete the previous character to correct a typo
+    Backspace,
+}
+
+/// A key typed after waiting `delay` since the previous one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Keystroke {
+    pub delay: Duration,
+    pub key: TypedKey,
+}
+
+/// Typing rhythm settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cadence {
+    /// Average time between two keys
+    pub base_delay: Duration,
+    /// How much a delay can differ from the average, as a fraction of it
+    pub variation: f64,
+    /// Extra time before the first key of a word
+    pub word_pause: Duration,
+    /// Extra time after punctuation
+    pub punctuation_pause: Duration,
+    /// Delay multiplier for the second key of a pair in [`FAST_DIGRAPHS`]
+    pub digraph_factor: f64,
+    /// Chance from 0.0 to 1.0 to hit a neighbouring key before the right one
+    pub typo_rate: f64,
+    /// Time to notice a typo before pressing backspace
+    pub correction_pause: Duration,
+    /// Seed of the random generator, a new seed is picked for every schedule if `None`
+    pub seed: Option<u64>,
+}
+
+impl Default for Cadence {
+    fn default() -> Self {
+        Cadence {
+            base_delay: Duration::from_millis(120),
+            variation: 0.3,
+            word_pause: Duration::from_millis(150),
+            punctuation_pause: Duration::from_millis(300),
+            digraph_factor: 0.7,
+            typo_rate: 0.0,
+            correction_pause: Duration::from_millis(250),
+            seed: None,
+        }
+    }
+}
+
+impl Cadence {
+    pub fn new() -> Cadence {
+        Cadence::default()
+    }
+
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Cadence {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn with_variation(mut self, variation: f64) -> Cadence {
+        self.variation = variation.max(0.0);
+        self
+    }
+
+    pub fn with_word_pause(mut self, word_pause: Duration) -> Cadence {
+        self.word_pause = word_pause;
+        self
+    }
+
+    pub fn with_punctuation_pause(mut self, punctuation_pause: Duration) -> Cadence {
+        self.punctuation_pause = punctuation_pause;
+        self
+    }
+
+    pub fn with_digraph_factor(mut self, digraph_factor: f64) -> Cadence {
+        self.digraph_factor = digraph_factor.max(0.0);
+        self
+    }
+
+    pub fn with_typo_rate(mut self, typo_rate: f64) -> Cadence {
+        self.typo_rate = typo_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_correction_pause(mut self, correction_pause: Duration) -> Cadence {
+        self.correction_pause = correction_pause;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Cadence {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Type `text` with this cadence when simulated
+    pub fn typing(self, text: impl Into<String>) -> Typing {
+        Typing {
+            text: text.into(),
+            cadence: self,
+        }
+    }
+
+    /// Keystrokes that type `text`.
+    /// ```
+    /// # use std::time::Duration;
+    /// # use kemuler_windows::cadence::*;
+    /// let cadence = Cadence::new()
+    ///     .with_base_delay(Duration::from_millis(100))
+    ///     .with_variation(0.0);
+    /// let delays = cadence
+    ///     .schedule("a. th")
+    ///     .iter()
+    ///     .map(|keystroke| keystroke.delay.as_millis())
+    ///     .collect::<Vec<_>>();
+    /// // the space is typed after a punctuation pause,
+    /// // "t" after a word pause and "h" faster as part of "th"
+    /// assert_eq!(delays, [100, 100, 400, 250, 70]);
+    ///
+    /// let keys = cadence
+    ///     .with_typo_rate(1.0)
+    ///     .with_seed(7)
+    ///     .schedule("a!")
+    ///     .iter()
+    ///     .map(|keystroke| keystroke.key)
+    ///     .collect::<Vec<_>>();
+    /// // "!" has no neighbouring key to mistype
+    /// assert!(matches!(
+    ///     keys[..],
+    ///     [TypedKey::Char('s'), TypedKey::Backspace, TypedKey::Char('a'), TypedKey::Char('!')]
+    /// ));
+    /// ```
+    pub fn schedule(&self, text: &str) -> Vec<Keystroke> {
+        let mut rng = SplitMix64::new(self.seed.unwrap_or_else(time_seed));
+        let mut keystrokes = Vec::new();
+        let mut previous = None;
+        for char in text.chars() {
+            let mut delay = self.varied(self.base_delay, &mut rng);
+            match previous {
+                Some(previous) if is_fast_digraph(previous, char) => {
+                    delay = delay.mul_f64(self.digraph_factor);
+                }
+                _ => {}
+            }
+            match previous {
+                Some(previous) if is_punctuation(previous) => delay += self.punctuation_pause,
+                Some(previous) if previous.is_whitespace() && !char.is_whitespace() => {
+                    delay += self.word_pause
+                }
+                _ => {}
+            }
+
+            let typo = if self.typo_rate > 0.0 && rng.next_f64() < self.typo_rate {
+                neighbour_key(char, &mut rng)
+            } else {
+                None
+            };
+            if let Some(typo) = typo {
+                keystrokes.push(Keystroke {
+                    delay,
+                    key: TypedKey::Char(typo),
+                });
+                keystrokes.push(Keystroke {
+                    delay: self.correction_pause + self.varied(self.base_delay, &mut rng),
+                    key: TypedKey::Backspace,
+                });
+                delay = self.varied(self.base_delay, &mut rng);
+            }
+            keystrokes.push(Keystroke {
+                delay,
+                key: TypedKey::Char(char),
+            });
+            previous = Some(char);
+        }
+        keystrokes
+    }
+
+    /// `delay` changed randomly by up to `variation`, most changes are small
+    fn varied(&self, delay: Duration, rng: &mut SplitMix64) -> Duration {
+        // the average of three uniform values is bell shaped
+        let noise = (0..3).map(|_| rng.next_f64() * 2.0 - 1.0).sum::<f64>() / 3.0;
+        delay.mul_f64((1.0 + noise * self.variation).max(0.0))
+    }
+}
+
+/// Text typed with a [`Cadence`] when simulated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Typing {
+    pub text: String,
+    pub cadence: Cadence,
+}
+
+impl Typing {
+    pub fn schedule(&self) -> Vec<Keystroke> {
+        self.cadence.schedule(&self.text)
+    }
+}
+
+fn is_punctuation(char: char) -> bool {
+    matches!(char, '.' | ',' | '!' | '?' | ';' | ':')
+}
+
+fn is_fast_digraph(first: char, second: char) -> bool {
+    let mut digraph = [0; 8];
+    let first_len = first.to_ascii_lowercase().encode_utf8(&mut digraph).len();
+    let second_len = second
+        .to_ascii_lowercase()
+        .encode_utf8(&mut digraph[first_len..])
+        .len();
+    std::str::from_utf8(&digraph[..first_len + second_len])
+        .is_ok_and(|digraph| FAST_DIGRAPHS.contains(&digraph))
+}
+
+/// A key next to `char` in the same keyboard row, keeping the case
+fn neighbour_key(char: char, rng: &mut SplitMix64) -> Option<char> {
+    let lowercase = char.to_ascii_lowercase();
+    let row = KEYBOARD_ROWS.iter().find(|row| row.contains(lowercase))?;
+    let row = row.chars().collect::<Vec<_>>();
+    let index = row.iter().position(|&c| c == lowercase)?;
+    let neighbours = [index.checked_sub(1), Some(index + 1)]
+        .into_iter()
+        .flatten()
+        .filter_map(|index| row.get(index))
+        .collect::<Vec<_>>();
+    let neighbour = *neighbours[rng.next_u64() as usize % neighbours.len()];
+    Some(if char.is_ascii_uppercase() {
+        neighbour.to_ascii_uppercase()
+    } else {
+        neighbour
+    })
+}
+
+fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
+
+/// Small seedable random generator, see <https://prng.di.unimi.it/splitmix64.c>
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64) -> SplitMix64 {
+        SplitMix64(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0.0..1.0`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
@@ -0,0 +1,292 @@
+//! Argument parsing for the `kemuler-windows` binary.
+//!
+//! Requires the `cli` feature.
+//! Every subcommand is translated into [`script`](crate::script) commands.
+//! ```
+//! # use kemuler_windows::{VirtualKey, cli::*, script::Command};
+//! let cli = Cli::parse(["--dry-run", "key", "ctrl+s"]).unwrap();
+//! assert!(cli.dry_run);
+//! assert_eq!(cli.action, Action::Commands(vec![
+//!     Command::Key(vec![vec![VirtualKey::Control, VirtualKey::S]]),
+//! ]));
+//! ```
+
+use std::{error::Error, fmt, path::PathBuf, str::FromStr};
+
+use crate::{
+    script::{self, Command},
+    MouseButton, VirtualKey,
+};
+
+/// Help printed by `kemuler-windows help`
+pub const USAGE: &str = "\
+Usage: kemuler-windows [--dry-run] <command> [arguments]
+
+Commands:
+  key <chord>...            Press and release keys, like `key ctrl+s enter`
+  type <text>...            Type text, arguments are joined with spaces
+  move <x> <y>              Move the mouse to a position in virtual desktop pixels
+  click [button] [count]    Click left, middle, right, x1 or x2, left by default, at most 1000 times
+  scroll <vertical> [horizontal]
+                            Scroll by wheel notches, negative is down and left
+  run <script>              Run a script file
+  serve [--tcp <address>]   Execute JSON lines from standard input or a loopback TCP address
+  keys --list               Print every virtual key with its code
+  help                      Print this help
+
+Options:
+  --dry-run                 Print the input to standard error instead of sending it
+";
+
+/// Parsed command line
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cli {
+    pub dry_run: bool,
+    pub action: Action,
+}
+
+/// What the binary does
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Run commands translated from a subcommand
+    Commands(Vec<Command>),
+    /// Run a script file
+    Run(PathBuf),
+    /// Run a [`server`](crate::server) on standard input or a TCP address
+    Serve { tcp: Option<String> },
+    /// Print [`key_list`]
+    ListKeys,
+    /// Print [`USAGE`]
+    Help,
+}
+
+/// Error returned by [`Cli::parse`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CliError {
+    MissingCommand,
+    UnknownCommand(String),
+    UnknownOption(String),
+    MissingArgument {
+        command: &'static str,
+        expected: &'static str,
+    },
+    UnexpectedArgument(String),
+    UnknownKey(String),
+    UnknownButton(String),
+    InvalidNumber(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::MissingCommand => write!(f, "missing command"),
+            CliError::UnknownCommand(command) => write!(f, "unknown command `{command}`"),
+            CliError::UnknownOption(option) => write!(f, "unknown option `{option}`"),
+            CliError::MissingArgument { command, expected } => {
+                write!(f, "`{command}` is missing {expected}")
+            }
+            CliError::UnexpectedArgument(argument) => {
+                write!(f, "unexpected argument `{argument}`")
+            }
+            CliError::UnknownKey(key) => write!(f, "unknown key `{key}`"),
+            CliError::UnknownButton(button) => write!(f, "unknown mouse button `{button}`"),
+            CliError::InvalidNumber(number) => write!(f, "invalid number `{number}`"),
+        }
+    }
+}
+
+impl Error for CliError {}
+
+impl Cli {
+    /// Parse arguments without the program name.
+    /// ```
+    /// # use kemuler_windows::{MouseButton, cli::*, script::Command};
+    /// let commands = |args: &[&str]| match Cli::parse(args.iter().copied()).unwrap().action {
+    ///     Action::Commands(commands) => commands,
+    ///     action => panic!("{action:?}"),
+    /// };
+    /// assert_eq!(commands(&["type", "hello", "world"]), [Command::Type("hello world".to_string())]);
+    /// assert_eq!(commands(&["move", "10", "-20"]), [Command::Move(10, -20)]);
+    /// assert_eq!(commands(&["click"]), [Command::Click { button: MouseButton::Left, count: 1 }]);
+    /// assert_eq!(commands(&["click", "right", "2"]), [Command::Click { button: MouseButton::Right, count: 2 }]);
+    /// assert_eq!(commands(&["scroll", "-3"]), [Command::Scroll { vertical: -3, horizontal: 0 }]);
+    ///
+    /// assert_eq!(Cli::parse(["keys", "--list"]).unwrap().action, Action::ListKeys);
+    /// assert_eq!(Cli::parse(["run", "a.txt"]).unwrap().action, Action::Run("a.txt".into()));
+    /// assert_eq!(Cli::parse(["serve"]).unwrap().action, Action::Serve { tcp: None });
+    /// assert_eq!(
+    ///     Cli::parse(["serve", "--tcp", "127.0.0.1:7878"]).unwrap().action,
+    ///     Action::Serve { tcp: Some("127.0.0.1:7878".to_string()) }
+    /// );
+    /// assert_eq!(Cli::parse(["move", "10"]), Err(CliError::MissingArgument { command: "move", expected: "a y position" }));
+    /// assert_eq!(Cli::parse(["key", "ctrl+nope"]), Err(CliError::UnknownKey("nope".to_string())));
+    /// assert_eq!(Cli::parse(["jump"]), Err(CliError::UnknownCommand("jump".to_string())));
+    /// assert_eq!(Cli::parse(["click", "left", "4294967295"]), Err(CliError::InvalidNumber("4294967295".to_string())));
+    /// assert_eq!(Cli::parse(["scroll", "99999999"]), Err(CliError::InvalidNumber("99999999".to_string())));
+    /// ```
+    pub fn parse<I>(args: I) -> Result<Cli, CliError>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into).peekable();
+        let mut dry_run = false;
+        while let Some(option) = args.next_if(|arg| arg.starts_with("--")) {
+            match option.as_str() {
+                "--dry-run" => dry_run = true,
+                "--help" => {
+                    return Ok(Cli {
+                        dry_run,
+                        action: Action::Help,
+                    })
+                }
+                _ => return Err(CliError::UnknownOption(option)),
+            }
+        }
+        let command = args.next().ok_or(CliError::MissingCommand)?;
+        let mut args = Args {
+            command: "",
+            args: args.collect::<Vec<_>>().into_iter(),
+        };
+        let action = match command.as_str() {
+            "key" => {
+                args.command = "key";
+                let mut chords = vec![chord(&args.required("a key")?)?];
+                for arg in args.args.by_ref() {
+                    chords.push(chord(&arg)?);
+                }
+                Action::Commands(vec![Command::Key(chords)])
+            }
+            "type" => {
+                args.command = "type";
+                let text = args.args.by_ref().collect::<Vec<_>>().join(" ");
+                if text.is_empty() {
+                    return Err(args.missing("text"));
+                }
+                Action::Commands(vec![Command::Type(text)])
+            }
+            "move" => {
+                args.command = "move";
+                let x = number(&args.required("an x position")?)?;
+                let y = number(&args.required("a y position")?)?;
+                Action::Commands(vec![Command::Move(x, y)])
+            }
+            "click" => {
+                args.command = "click";
+                let mut button = MouseButton::Left;
+                let mut count = None;
+                if let Some(arg) = args.args.next() {
+                    if arg.parse::<i64>().is_ok() {
+                        count = Some(arg);
+                    } else {
+                        button = button_from_name(&arg)?;
+                        count = args.args.next();
+                    }
+                }
+                let command = match count {
+                    Some(count) => Command::click(button, number(&count)?)
+                        .ok_or(CliError::InvalidNumber(count))?,
+                    None => Command::Click { button, count: 1 },
+                };
+                Action::Commands(vec![command])
+            }
+            "scroll" => {
+                args.command = "scroll";
+                let vertical = notches(&args.required("a scroll amount")?)?;
+                let horizontal = args.args.next().map(|arg| notches(&arg)).transpose()?;
+                Action::Commands(vec![Command::Scroll {
+                    vertical,
+                    horizontal: horizontal.unwrap_or(0),
+                }])
+            }
+            "run" => {
+                args.command = "run";
+                Action::Run(args.required("a script path")?.into())
+            }
+            "serve" => {
+                args.command = "serve";
+                let tcp = match args.args.next() {
+                    Some(option) if option == "--tcp" => Some(args.required("a TCP address")?),
+                    Some(option) => return Err(CliError::UnknownOption(option)),
+                    None => None,
+                };
+                Action::Serve { tcp }
+            }
+            "keys" => {
+                args.command = "keys";
+                match args.required("`--list`")?.as_str() {
+                    "--list" => Action::ListKeys,
+                    option => return Err(CliError::UnknownOption(option.to_string())),
+                }
+            }
+            "help" => Action::Help,
+            _ => return Err(CliError::UnknownCommand(command)),
+        };
+        args.finish()?;
+        Ok(Cli { dry_run, action })
+    }
+}
+
+/// Every [`VirtualKey`] with its code, one per line.
+/// ```
+/// # use kemuler_windows::cli::key_list;
+/// assert!(key_list().lines().any(|line| line == "Enter 0x0D VK_RETURN"));
+/// ```
+pub fn key_list() -> String {
+    VirtualKey::ALL
+        .iter()
+        .map(|key| format!("{key:?} 0x{:02X} {}\n", key.code().0, key.win32_name()))
+        .collect()
+}
+
+/// Remaining arguments of a subcommand
+struct Args {
+    command: &'static str,
+    args: std::vec::IntoIter<String>,
+}
+
+impl Args {
+    fn missing(&self, expected: &'static str) -> CliError {
+        CliError::MissingArgument {
+            command: self.command,
+            expected,
+        }
+    }
+
+    fn required(&mut self, expected: &'static str) -> Result<String, CliError> {
+        self.args.next().ok_or_else(|| self.missing(expected))
+    }
+
+    fn finish(mut self) -> Result<(), CliError> {
+        match self.args.next() {
+            Some(arg) => Err(CliError::UnexpectedArgument(arg)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn chord(arg: &str) -> Result<Vec<VirtualKey>, CliError> {
+    arg.split('+')
+        .map(|name| {
+            script::key_from_name(name).ok_or_else(|| CliError::UnknownKey(name.to_string()))
+        })
+        .collect()
+}
+
+fn button_from_name(arg: &str) -> Result<MouseButton, CliError> {
+    script::button_from_name(arg).ok_or_else(|| CliError::UnknownButton(arg.to_string()))
+}
+
+fn number<T: FromStr>(arg: &str) -> Result<T, CliError> {
+    arg.parse()
+        .map_err(|_| CliError::InvalidNumber(arg.to_string()))
+}
+
+/// Wheel notches checked like [`Command::scroll`]
+fn notches(arg: &str) -> Result<i32, CliError> {
+    let notches = number(arg)?;
+    match Command::scroll(notches, 0) {
+        Some(_) => Ok(notches),
+        None => Err(CliError::InvalidNumber(arg.to_string())),
+    }
+}
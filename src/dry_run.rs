@@ -0,0 +1,338 @@
+//! Describe input instead of sending it.
+//!
+//! A [`Windows`](crate::Windows) in dry-run mode converts every event to the structure
+//! it would pass to Windows and writes a description of it to a [`DryRunSink`]
+//! instead of injecting it.
+//! ```
+//! # use kemuler::{common_inputs::Char, simulator::Simulate, input_event::SetTo};
+//! # use kemuler_windows::{VirtualKey, MouseButton, Windows, dry_run::*};
+//! let recording = Recording::new();
+//! let mut windows = Windows::new().with_signature(0).with_dry_run(recording.clone());
+//! windows.simulate(VirtualKey::LShift.down());
+//! windows.simulate(SetTo::new(Char('é'), true));
+//! windows.simulate(MouseButton::X1.down());
+//! assert_eq!(recording.lines(), [
+//!     "KEYDOWN VK_LSHIFT scan=0x00 ext=0",
+//!     "KEYDOWN UNICODE U+00E9 'é'",
+//!     "MOUSE XDOWN X1",
+//! ]);
+//! ```
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use windows::Win32::UI::{
+    Input::{KeyboardAndMouse, Pointer},
+    WindowsAndMessaging,
+};
+
+use crate::{inner::send_input::WindowsSendInputEnum, VirtualKey};
+
+/// Destination of dry-run descriptions, one line per event.
+///
+/// Closures taking a `&str` implement this trait.
+pub trait DryRunSink: Send + Sync {
+    fn log(&self, line: &str);
+}
+
+impl<F> DryRunSink for F
+where
+    F: Fn(&str) + Send + Sync,
+{
+    fn log(&self, line: &str) {
+        self(line)
+    }
+}
+
+/// Print every line to standard error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Stderr;
+
+impl DryRunSink for Stderr {
+    fn log(&self, line: &str) {
+        eprintln!("{}", line);
+    }
+}
+
+/// Keep every line in memory, clones share the lines
+#[derive(Debug, Clone, Default)]
+pub struct Recording {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Recording {
+    pub fn new() -> Recording {
+        Recording::default()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().unwrap().clear();
+    }
+}
+
+impl DryRunSink for Recording {
+    fn log(&self, line: &str) {
+        self.lines.lock().unwrap().push(line.to_string());
+    }
+}
+
+/// Dry-run configuration of [`Windows`](crate::Windows)
+#[derive(Clone)]
+pub(crate) struct DryRun {
+    sink: Arc<dyn DryRunSink>,
+}
+
+impl fmt::Debug for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DryRun").finish_non_exhaustive()
+    }
+}
+
+impl DryRun {
+    pub(crate) fn new(sink: impl DryRunSink + 'static) -> DryRun {
+        DryRun {
+            sink: Arc::new(sink),
+        }
+    }
+
+    pub(crate) fn log_inputs(&self, inputs: &[WindowsSendInputEnum], extra_info: usize) {
+        for input in inputs {
+            self.sink.log(&describe(&input.into_windows(extra_info)));
+        }
+    }
+
+    pub(crate) fn log_touch(&self, contacts: &[Pointer::POINTER_TOUCH_INFO]) {
+        for contact in contacts {
+            self.sink.log(&describe_touch(contact));
+        }
+    }
+
+    pub(crate) fn log_pen(&self, pen: &Pointer::POINTER_PEN_INFO) {
+        self.sink.log(&describe_pen(pen));
+    }
+}
+
+/// Human-readable description of an `INPUT` passed to `SendInput`.
+/// ```
+/// # use kemuler_windows::{HardwareInput, dry_run::describe};
+/// # use windows::Win32::UI::Input::KeyboardAndMouse::*;
+/// let input = INPUT {
+///     r#type: INPUT_KEYBOARD,
+///     Anonymous: INPUT_0 {
+///         ki: KEYBDINPUT {
+///             wVk: VK_LSHIFT,
+///             wScan: 0x2A,
+///             dwFlags: KEYBD_EVENT_FLAGS(0),
+///             ..Default::default()
+///         },
+///     },
+/// };
+/// assert_eq!(describe(&input), "KEYDOWN VK_LSHIFT scan=0x2A ext=0");
+///
+/// let input = INPUT {
+///     r#type: INPUT_KEYBOARD,
+///     Anonymous: INPUT_0 {
+///         ki: KEYBDINPUT {
+///             wVk: VK_RCONTROL,
+///             wScan: 0x1D,
+///             dwFlags: KEYEVENTF_KEYUP | KEYEVENTF_EXTENDEDKEY,
+///             ..Default::default()
+///         },
+///     },
+/// };
+/// assert_eq!(describe(&input), "KEYUP VK_RCONTROL scan=0x1D ext=1");
+///
+/// let input = INPUT {
+///     r#type: INPUT_MOUSE,
+///     Anonymous: INPUT_0 {
+///         mi: MOUSEINPUT {
+///             dx: 32768,
+///             dy: 16384,
+///             dwFlags: MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
+///             ..Default::default()
+///         },
+///     },
+/// };
+/// assert_eq!(describe(&input), "MOUSE MOVE abs virtualdesk 32768,16384");
+///
+/// let input = INPUT {
+///     r#type: INPUT_MOUSE,
+///     Anonymous: INPUT_0 {
+///         mi: MOUSEINPUT {
+///             dx: -5,
+///             dy: 10,
+///             mouseData: -120,
+///             dwFlags: MOUSEEVENTF_MOVE | MOUSEEVENTF_WHEEL,
+///             ..Default::default()
+///         },
+///     },
+/// };
+/// assert_eq!(describe(&input), "MOUSE MOVE rel -5,10 + WHEEL -120");
+///
+/// let input = HardwareInput::new(0x0401, 1, 2).unwrap().into_windows(0);
+/// assert_eq!(describe(&input), "HARDWARE msg=0x0401 wParamL=0x0001 wParamH=0x0002");
+/// ```
+pub fn describe(input: &KeyboardAndMouse::INPUT) -> String {
+    match input.r#type {
+        KeyboardAndMouse::INPUT_KEYBOARD => {
+            let ki = unsafe { input.Anonymous.ki };
+            let has_flag = |flag: KeyboardAndMouse::KEYBD_EVENT_FLAGS| (ki.dwFlags & flag).0 != 0;
+            let action = if has_flag(KeyboardAndMouse::KEYEVENTF_KEYUP) {
+                "KEYUP"
+            } else {
+                "KEYDOWN"
+            };
+            if has_flag(KeyboardAndMouse::KEYEVENTF_UNICODE) {
+                let char = char::from_u32(ki.wScan as u32)
+                    .map(|char| format!(" {:?}", char))
+                    .unwrap_or_default();
+                format!("{} UNICODE U+{:04X}{}", action, ki.wScan, char)
+            } else {
+                let name = VirtualKey::from_code(ki.wVk.0)
+                    .map(|key| key.win32_name().to_string())
+                    .unwrap_or_else(|| format!("VK_0x{:02X}", ki.wVk.0));
+                format!(
+                    "{} {} scan=0x{:02X} ext={}",
+                    action,
+                    name,
+                    ki.wScan,
+                    has_flag(KeyboardAndMouse::KEYEVENTF_EXTENDEDKEY) as u8
+                )
+            }
+        }
+        KeyboardAndMouse::INPUT_MOUSE => {
+            let mi = unsafe { input.Anonymous.mi };
+            let has_flag = |flag: KeyboardAndMouse::MOUSE_EVENT_FLAGS| (mi.dwFlags & flag).0 != 0;
+            let x_button = match mi.mouseData as u16 {
+                WindowsAndMessaging::XBUTTON1 => "X1",
+                WindowsAndMessaging::XBUTTON2 => "X2",
+                _ => "X?",
+            };
+            let mut actions = Vec::new();
+            if has_flag(KeyboardAndMouse::MOUSEEVENTF_MOVE) {
+                if has_flag(KeyboardAndMouse::MOUSEEVENTF_ABSOLUTE) {
+                    let desktop = if has_flag(KeyboardAndMouse::MOUSEEVENTF_VIRTUALDESK) {
+                        "virtualdesk"
+                    } else {
+                        "primary"
+                    };
+                    actions.push(format!("MOVE abs {} {},{}", desktop, mi.dx, mi.dy));
+                } else {
+                    actions.push(format!("MOVE rel {},{}", mi.dx, mi.dy));
+                }
+            }
+            let buttons = [
+                (KeyboardAndMouse::MOUSEEVENTF_LEFTDOWN, "LEFTDOWN"),
+                (KeyboardAndMouse::MOUSEEVENTF_LEFTUP, "LEFTUP"),
+                (KeyboardAndMouse::MOUSEEVENTF_RIGHTDOWN, "RIGHTDOWN"),
+                (KeyboardAndMouse::MOUSEEVENTF_RIGHTUP, "RIGHTUP"),
+                (KeyboardAndMouse::MOUSEEVENTF_MIDDLEDOWN, "MIDDLEDOWN"),
+                (KeyboardAndMouse::MOUSEEVENTF_MIDDLEUP, "MIDDLEUP"),
+            ];
+            for (flag, name) in buttons {
+                if has_flag(flag) {
+                    actions.push(name.to_string());
+                }
+            }
+            if has_flag(KeyboardAndMouse::MOUSEEVENTF_XDOWN) {
+                actions.push(format!("XDOWN {}", x_button));
+            }
+            if has_flag(KeyboardAndMouse::MOUSEEVENTF_XUP) {
+                actions.push(format!("XUP {}", x_button));
+            }
+            if has_flag(KeyboardAndMouse::MOUSEEVENTF_WHEEL) {
+                actions.push(format!("WHEEL {}", mi.mouseData));
+            }
+            if has_flag(KeyboardAndMouse::MOUSEEVENTF_HWHEEL) {
+                actions.push(format!("HWHEEL {}", mi.mouseData));
+            }
+            if actions.is_empty() {
+                actions.push("NONE".to_string());
+            }
+            format!("MOUSE {}", actions.join(" + "))
+        }
+        KeyboardAndMouse::INPUT_HARDWARE => {
+            let hi = unsafe { input.Anonymous.hi };
+            format!(
+                "HARDWARE msg=0x{:04X} wParamL=0x{:04X} wParamH=0x{:04X}",
+                hi.uMsg, hi.wParamL, hi.wParamH
+            )
+        }
+        input_type => format!("UNKNOWN type={}", input_type.0),
+    }
+}
+
+/// Phase of a pointer from its flags
+fn pointer_phase(flags: Pointer::POINTER_FLAGS) -> &'static str {
+    let has_flag = |flag: Pointer::POINTER_FLAGS| (flags & flag).0 != 0;
+    if has_flag(Pointer::POINTER_FLAG_DOWN) {
+        "DOWN"
+    } else if has_flag(Pointer::POINTER_FLAG_UP) {
+        "UP"
+    } else if has_flag(Pointer::POINTER_FLAG_INRANGE) {
+        "UPDATE"
+    } else {
+        "LEAVE"
+    }
+}
+
+/// Human-readable description of a touch contact passed to `InjectTouchInput`.
+/// ```
+/// # use kemuler_windows::{dry_run::describe_touch, touch::TouchContact};
+/// let contact = TouchContact::down(1, (500, 300)).with_pressure(512).into_windows();
+/// assert_eq!(describe_touch(&contact), "TOUCH DOWN id=1 500,300 pressure=512");
+/// ```
+pub fn describe_touch(contact: &Pointer::POINTER_TOUCH_INFO) -> String {
+    let info = &contact.pointerInfo;
+    let mut description = format!(
+        "TOUCH {} id={} {},{}",
+        pointer_phase(info.pointerFlags),
+        info.pointerId,
+        info.ptPixelLocation.x,
+        info.ptPixelLocation.y
+    );
+    if contact.touchMask & WindowsAndMessaging::TOUCH_MASK_PRESSURE != 0 {
+        description += &format!(" pressure={}", contact.pressure);
+    }
+    if contact.touchMask & WindowsAndMessaging::TOUCH_MASK_ORIENTATION != 0 {
+        description += &format!(" orientation={}", contact.orientation);
+    }
+    description
+}
+
+/// Human-readable description of a pen state passed to `InjectSyntheticPointerInput`.
+/// ```
+/// # use kemuler_windows::{dry_run::describe_pen, pen::*};
+/// let hovering = PenState { position: (400, 300), is_in_range: true, ..Default::default() };
+/// let touching = PenState { pressure: 600, ..hovering.with_button(PenButton::Tip, true) };
+/// assert_eq!(
+///     describe_pen(&touching.into_windows(&hovering)),
+///     "PEN DOWN 400,300 pressure=600 tilt=0,0 rotation=0"
+/// );
+/// ```
+pub fn describe_pen(pen: &Pointer::POINTER_PEN_INFO) -> String {
+    let info = &pen.pointerInfo;
+    let mut description = format!(
+        "PEN {} {},{} pressure={} tilt={},{} rotation={}",
+        pointer_phase(info.pointerFlags),
+        info.ptPixelLocation.x,
+        info.ptPixelLocation.y,
+        pen.pressure,
+        pen.tiltX,
+        pen.tiltY,
+        pen.rotation
+    );
+    if pen.penFlags & WindowsAndMessaging::PEN_FLAG_BARREL != 0 {
+        description += " barrel";
+    }
+    if pen.penFlags & WindowsAndMessaging::PEN_FLAG_ERASER != 0 {
+        description += " eraser";
+    }
+    description
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+use crate::{pen::PenError, touch::TouchError, window::Window};
+
+/// Error returned when [`Windows`](crate::Windows) refuses to send input
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The foreground window is not the window guarded by a
+    /// [`ForegroundGuard`](crate::guard::ForegroundGuard)
+    FocusLost {
+        expected: Window,
+        actual: Option<Window>,
+    },
+    /// The window does not exist anymore
+    WindowUnavailable(Window),
+    /// The touch frame cannot be injected
+    InvalidTouchFrame(TouchError),
+    /// The pen state cannot be injected
+    InvalidPenState(PenError),
+    /// The [`KillSwitch`](crate::kill_switch::KillSwitch) was tripped
+    KillSwitchTripped,
+    /// Windows failed to inject the input
+    Injection(windows::core::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::FocusLost { expected, actual } => {
+                write!(f, "focus moved from window {:?} to ", expected.hwnd.0)?;
+                match actual {
+                    Some(actual) => write!(f, "window {:?}", actual.hwnd.0),
+                    None => write!(f, "no window"),
+                }
+            }
+            Error::WindowUnavailable(window) => {
+                write!(f, "window {:?} does not exist", window.hwnd.0)
+            }
+            Error::InvalidTouchFrame(error) => write!(f, "invalid touch frame: {}", error),
+            Error::InvalidPenState(error) => write!(f, "invalid pen state: {}", error),
+            Error::KillSwitchTripped => write!(f, "kill switch tripped"),
+            Error::Injection(error) => write!(f, "failed to inject input: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidTouchFrame(error) => Some(error),
+            Error::InvalidPenState(error) => Some(error),
+            Error::Injection(error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/// What to write into `dwExtraInfo` of every event sent by [`Windows`](crate::Windows).
+///
+/// Low-level hooks receive this value along with the event,
+/// so a fixed signature lets them recognise input sent by this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ExtraInfo {
+    /// Use `GetMessageExtraInfo`, this is the default.
+    #[default]
+    MessageExtraInfo,
+    /// Use a fixed signature
+    Signature(usize),
+}
+
+impl ExtraInfo {
+    /// Return true if an observed event's extra info matches this signature.
+    ///
+    /// Always return false for [`ExtraInfo::MessageExtraInfo`]
+    /// since there is no signature to match against.
+    /// ```
+    /// # use kemuler_windows::ExtraInfo;
+    /// let signature = ExtraInfo::Signature(0x4B454D55);
+    /// assert!(signature.matches(0x4B454D55));
+    /// assert!(!signature.matches(0));
+    /// assert!(!ExtraInfo::MessageExtraInfo.matches(0));
+    /// ```
+    pub fn matches(&self, observed: usize) -> bool {
+        match self {
+            ExtraInfo::MessageExtraInfo => false,
+            ExtraInfo::Signature(signature) => *signature == observed,
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Refuse to send input when focus moves away from the intended window.
+//!
+//! `SendInput` always sends to whatever window is in the foreground.
+//! A [`ForegroundGuard`] set on [`Windows`](crate::Windows) captures the intended window
+//! and checks the foreground window before every batch of input,
+//! so input is not sent to a notification or a window the user switched to.
+//! ```no_run
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{VirtualKey, Windows, TrySimulate, guard::ForegroundGuard};
+//! let mut windows = Windows::new().with_foreground_guard(ForegroundGuard::capture().unwrap());
+//! windows.try_simulate(VirtualKey::A.down()).unwrap();
+//! ```
+//!
+//! With a [`Timing`](crate::timing::Timing) the foreground window is checked again before every
+//! paced event, so focus moving while the simulator waits stops the rest of the input.
+//! ```
+//! # use std::{sync::Arc, time::Duration};
+//! # use kemuler::{common_inputs::MouseScroll, input_event::ChangeBy};
+//! # use kemuler_windows::{Error, TrySimulate, Windows, dry_run::Recording, guard::ForegroundGuard, timing::*, window::Window};
+//! # use windows::Win32::Foundation::HWND;
+//! let (target, chat) = (Window::new(HWND(1)), Window::new(HWND(2)));
+//! let clock = Arc::new(ManualClock::new());
+//! let now = clock.clone();
+//! // a chat window pops up 5ms from now
+//! let guard = ForegroundGuard::new(target)
+//!     .with_query(move || Some(if now.now() < Duration::from_millis(5) { target } else { chat }));
+//! let log = Recording::new();
+//! let mut windows = Windows::new()
+//!     .with_signature(0)
+//!     .with_dry_run(log.clone())
+//!     .with_clock(clock.clone())
+//!     .with_timing(Timing::new().with_between_events(Duration::from_millis(10)))
+//!     .with_foreground_guard(guard);
+//! // a vertical and a horizontal wheel event, 10ms apart
+//! let result = windows.try_simulate(ChangeBy::new(MouseScroll, (120, 120)));
+//! assert_eq!(result, Err(Error::FocusLost { expected: target, actual: Some(chat) }));
+//! assert_eq!(log.lines().len(), 1);
+//! ```
+
+use std::{
+    fmt,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{window::Window, Error};
+
+/// How often a waiting [`ForegroundGuard`] checks the foreground window again
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Source of the foreground window.
+///
+/// [`SystemForeground`] asks Windows, other implementations are useful for testing.
+/// Closures returning `Option<Window>` implement this trait.
+pub trait ForegroundQuery: Send + Sync {
+    fn foreground(&self) -> Option<Window>;
+}
+
+/// Query the foreground window with `GetForegroundWindow`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SystemForeground;
+
+impl ForegroundQuery for SystemForeground {
+    fn foreground(&self) -> Option<Window> {
+        Window::foreground()
+    }
+}
+
+impl<F> ForegroundQuery for F
+where
+    F: Fn() -> Option<Window> + Send + Sync,
+{
+    fn foreground(&self) -> Option<Window> {
+        self()
+    }
+}
+
+/// What a [`ForegroundGuard`] does when the target window is not in the foreground
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OnFocusLost {
+    /// Refuse to send input right away
+    #[default]
+    Refuse,
+    /// Pause until the target window is back in the foreground,
+    /// and refuse if that takes longer than the timeout
+    Wait(Duration),
+}
+
+/// Only allow input while a target window is in the foreground.
+/// ```
+/// # use kemuler_windows::{Error, guard::*, window::Window};
+/// # use windows::Win32::Foundation::HWND;
+/// let target = Window::new(HWND(1));
+/// let chat = Window::new(HWND(2));
+///
+/// let guard = ForegroundGuard::new(target).with_query(move || Some(target));
+/// assert_eq!(guard.check(), Ok(()));
+///
+/// let guard = guard.with_query(move || Some(chat));
+/// assert_eq!(
+///     guard.check(),
+///     Err(Error::FocusLost {
+///         expected: target,
+///         actual: Some(chat),
+///     })
+/// );
+/// ```
+#[derive(Clone)]
+pub struct ForegroundGuard {
+    target: Window,
+    query: Arc<dyn ForegroundQuery>,
+    on_focus_lost: OnFocusLost,
+}
+
+impl ForegroundGuard {
+    pub fn new(target: Window) -> ForegroundGuard {
+        ForegroundGuard {
+            target,
+            query: Arc::new(SystemForeground),
+            on_focus_lost: OnFocusLost::default(),
+        }
+    }
+
+    /// Guard the current foreground window.
+    /// Return `None` if there is no foreground window.
+    pub fn capture() -> Option<ForegroundGuard> {
+        Window::foreground().map(ForegroundGuard::new)
+    }
+
+    pub fn with_query(mut self, query: impl ForegroundQuery + 'static) -> ForegroundGuard {
+        self.query = Arc::new(query);
+        self
+    }
+
+    pub fn with_on_focus_lost(mut self, on_focus_lost: OnFocusLost) -> ForegroundGuard {
+        self.on_focus_lost = on_focus_lost;
+        self
+    }
+
+    pub fn target(&self) -> Window {
+        self.target
+    }
+
+    /// Return an error if the target window is not in the foreground,
+    /// after waiting for it if [`OnFocusLost::Wait`] is used.
+    pub fn check(&self) -> Result<(), Error> {
+        let start = Instant::now();
+        loop {
+            let actual = self.query.foreground();
+            if actual == Some(self.target) {
+                return Ok(());
+            }
+            match self.on_focus_lost {
+                OnFocusLost::Wait(timeout) if start.elapsed() < timeout => {
+                    thread::sleep(POLL_INTERVAL)
+                }
+                _ => {
+                    return Err(Error::FocusLost {
+                        expected: self.target,
+                        actual,
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ForegroundGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ForegroundGuard")
+            .field("target", &self.target)
+            .field("on_focus_lost", &self.on_focus_lost)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,133 @@
+use std::{error::Error, fmt};
+
+use windows::Win32::UI::{Input::KeyboardAndMouse, WindowsAndMessaging};
+
+use crate::inner::send_input::WindowsSendInputEnum;
+
+/// Input from a device other than keyboard and mouse, sent as `INPUT_HARDWARE`.
+/// ```no_run
+/// # use kemuler::simulator::Simulate;
+/// # use kemuler_windows::{HardwareInput, Windows};
+/// Windows::new().simulate(HardwareInput::new(0x0401, 0x0001, 0x0000).unwrap());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HardwareInput {
+    message: u32,
+    w_param_low: u16,
+    w_param_high: u16,
+}
+
+/// Error returned by [`HardwareInput::new`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HardwareInputError {
+    /// Message 0 is `WM_NULL`
+    NullMessage,
+    /// Keyboard messages must be sent as keyboard input
+    KeyboardMessage(u32),
+    /// Mouse messages must be sent as mouse input
+    MouseMessage(u32),
+    /// Messages above `0xFFFF` are reserved by the system
+    ReservedMessage(u32),
+}
+
+impl fmt::Display for HardwareInputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HardwareInputError::NullMessage => write!(f, "hardware input message is WM_NULL"),
+            HardwareInputError::KeyboardMessage(message) => {
+                write!(f, "message {message:#06x} is a keyboard message")
+            }
+            HardwareInputError::MouseMessage(message) => {
+                write!(f, "message {message:#06x} is a mouse message")
+            }
+            HardwareInputError::ReservedMessage(message) => {
+                write!(f, "message {message:#x} is reserved by the system")
+            }
+        }
+    }
+}
+
+impl Error for HardwareInputError {}
+
+impl HardwareInput {
+    /// Hardware input with `message` and the low and high words of its `wParam`.
+    /// ```
+    /// # use kemuler_windows::{HardwareInput, HardwareInputError};
+    /// assert!(HardwareInput::new(0x0401, 1, 2).is_ok());
+    /// assert_eq!(HardwareInput::new(0, 1, 2), Err(HardwareInputError::NullMessage));
+    /// // WM_KEYDOWN
+    /// assert_eq!(HardwareInput::new(0x0100, 1, 2), Err(HardwareInputError::KeyboardMessage(0x0100)));
+    /// // WM_LBUTTONDOWN
+    /// assert_eq!(HardwareInput::new(0x0201, 1, 2), Err(HardwareInputError::MouseMessage(0x0201)));
+    /// assert_eq!(HardwareInput::new(0x10000, 1, 2), Err(HardwareInputError::ReservedMessage(0x10000)));
+    /// ```
+    pub fn new(
+        message: u32,
+        w_param_low: u16,
+        w_param_high: u16,
+    ) -> Result<HardwareInput, HardwareInputError> {
+        match message {
+            0 => Err(HardwareInputError::NullMessage),
+            WindowsAndMessaging::WM_KEYFIRST..=WindowsAndMessaging::WM_KEYLAST => {
+                Err(HardwareInputError::KeyboardMessage(message))
+            }
+            WindowsAndMessaging::WM_MOUSEFIRST..=WindowsAndMessaging::WM_MOUSELAST => {
+                Err(HardwareInputError::MouseMessage(message))
+            }
+            0x10000.. => Err(HardwareInputError::ReservedMessage(message)),
+            _ => Ok(HardwareInput {
+                message,
+                w_param_low,
+                w_param_high,
+            }),
+        }
+    }
+
+    /// Hardware input with `message` and a whole `wParam`.
+    /// ```
+    /// # use kemuler_windows::HardwareInput;
+    /// let input = HardwareInput::from_w_param(0x0401, 0x0002_0001).unwrap();
+    /// assert_eq!(input, HardwareInput::new(0x0401, 0x0001, 0x0002).unwrap());
+    /// assert_eq!(input.w_param(), 0x0002_0001);
+    /// ```
+    pub fn from_w_param(message: u32, w_param: u32) -> Result<HardwareInput, HardwareInputError> {
+        HardwareInput::new(message, w_param as u16, (w_param >> 16) as u16)
+    }
+
+    pub fn message(&self) -> u32 {
+        self.message
+    }
+
+    pub fn w_param_low(&self) -> u16 {
+        self.w_param_low
+    }
+
+    pub fn w_param_high(&self) -> u16 {
+        self.w_param_high
+    }
+
+    pub fn w_param(&self) -> u32 {
+        (self.w_param_high as u32) << 16 | self.w_param_low as u32
+    }
+
+    /// Convert into the structure passed to `SendInput`
+    /// ```
+    /// # use kemuler_windows::HardwareInput;
+    /// # use windows::Win32::UI::Input::KeyboardAndMouse::*;
+    /// let input = HardwareInput::new(0x0401, 3, 4).unwrap().into_windows(0);
+    /// assert_eq!(input.r#type, INPUT_HARDWARE);
+    /// let hi = unsafe { input.Anonymous.hi };
+    /// assert_eq!((hi.uMsg, hi.wParamL, hi.wParamH), (0x0401, 3, 4));
+    /// ```
+    pub fn into_windows(self, extra_info: usize) -> KeyboardAndMouse::INPUT {
+        self.into_send_input().into_windows(extra_info)
+    }
+
+    pub(crate) fn into_send_input(self) -> WindowsSendInputEnum {
+        WindowsSendInputEnum::Hardware {
+            uMsg: self.message,
+            wParamL: self.w_param_low,
+            wParamH: self.w_param_high,
+        }
+    }
+}
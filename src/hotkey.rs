@@ -0,0 +1,283 @@
+//! Global hotkeys with `RegisterHotKey`.
+//!
+//! [`HotkeyManager`] registers [`Hotkey`]s on a dedicated thread running a message loop
+//! and reports every triggered hotkey to a callback or over a channel.
+//! ```no_run
+//! # use kemuler_windows::{VirtualKey, hotkey::*};
+//! let start = Hotkey::from_chord(&[VirtualKey::Control, VirtualKey::Alt, VirtualKey::S]).unwrap();
+//! let (manager, triggered) = HotkeyManager::start([start]).unwrap();
+//! for hotkey in triggered.iter() {
+//!     println!("{} pressed", hotkey);
+//! }
+//! ```
+//!
+//! Callbacks run on the manager thread and can simulate input directly.
+//! ```no_run
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{VirtualKey, Windows, hotkey::*};
+//! let hotkey = Hotkey::new([Modifier::Win], VirtualKey::F1);
+//! let mut windows = Windows::new();
+//! let manager = HotkeyManager::start_with_callback([hotkey], move |_| {
+//!     windows.simulate(VirtualKey::Enter.down());
+//!     windows.simulate(VirtualKey::Enter.up());
+//! })
+//! .unwrap();
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+};
+
+use windows::Win32::{
+    System::Threading::GetCurrentThreadId,
+    UI::{
+        Input::KeyboardAndMouse::{self, HOT_KEY_MODIFIERS},
+        WindowsAndMessaging,
+    },
+};
+
+use crate::{inner, VirtualKey};
+
+/// Modifier keys usable in a [`Hotkey`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Modifier {
+    Alt,
+    Control,
+    Shift,
+    Win,
+}
+
+impl Modifier {
+    /// Modifier represented by `key`, left and right variants are treated the same.
+    /// Return `None` if `key` is not a modifier key.
+    pub fn from_virtual_key(key: VirtualKey) -> Option<Modifier> {
+        match key {
+            VirtualKey::Alt | VirtualKey::LAlt | VirtualKey::RAlt => Some(Modifier::Alt),
+            VirtualKey::Control | VirtualKey::LControl | VirtualKey::RControl => {
+                Some(Modifier::Control)
+            }
+            VirtualKey::Shift | VirtualKey::LShift | VirtualKey::RShift => Some(Modifier::Shift),
+            VirtualKey::LWin | VirtualKey::RWin => Some(Modifier::Win),
+            _ => None,
+        }
+    }
+
+    pub fn flag(self) -> HOT_KEY_MODIFIERS {
+        match self {
+            Modifier::Alt => KeyboardAndMouse::MOD_ALT,
+            Modifier::Control => KeyboardAndMouse::MOD_CONTROL,
+            Modifier::Shift => KeyboardAndMouse::MOD_SHIFT,
+            Modifier::Win => KeyboardAndMouse::MOD_WIN,
+        }
+    }
+}
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Error returned by [`Hotkey::from_chord`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChordError {
+    /// The chord only has modifier keys
+    NoKey,
+    /// The chord has more than one non-modifier key
+    MultipleKeys(VirtualKey, VirtualKey),
+}
+
+impl fmt::Display for ChordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChordError::NoKey => write!(f, "chord has no non-modifier key"),
+            ChordError::MultipleKeys(a, b) => {
+                write!(f, "chord has more than one non-modifier key: {a} and {b}")
+            }
+        }
+    }
+}
+
+impl Error for ChordError {}
+
+/// A key with modifiers that can be registered as a global hotkey
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    pub modifiers: Vec<Modifier>,
+    pub key: VirtualKey,
+    /// Do not trigger again while the hotkey is held down, this is `MOD_NOREPEAT`.
+    pub no_repeat: bool,
+}
+
+impl Hotkey {
+    pub fn new(modifiers: impl IntoIterator<Item = Modifier>, key: VirtualKey) -> Hotkey {
+        Hotkey {
+            modifiers: modifiers.into_iter().collect(),
+            key,
+            no_repeat: true,
+        }
+    }
+
+    /// Build a hotkey from keys that are pressed together.
+    /// Every key must be a modifier key except exactly one.
+    /// Repeat is disabled.
+    /// ```
+    /// # use kemuler_windows::{VirtualKey, hotkey::*};
+    /// let hotkey = Hotkey::from_chord(&[VirtualKey::LControl, VirtualKey::Shift, VirtualKey::F9]).unwrap();
+    /// assert_eq!(hotkey, Hotkey::new([Modifier::Control, Modifier::Shift], VirtualKey::F9));
+    /// assert_eq!(hotkey.to_string(), "Control+Shift+F9");
+    ///
+    /// assert_eq!(
+    ///     Hotkey::from_chord(&[VirtualKey::Control, VirtualKey::A, VirtualKey::B]),
+    ///     Err(ChordError::MultipleKeys(VirtualKey::A, VirtualKey::B))
+    /// );
+    /// assert_eq!(Hotkey::from_chord(&[VirtualKey::Alt]), Err(ChordError::NoKey));
+    /// ```
+    pub fn from_chord(chord: &[VirtualKey]) -> Result<Hotkey, ChordError> {
+        let mut modifiers = Vec::new();
+        let mut key = None;
+        for &chord_key in chord {
+            match (Modifier::from_virtual_key(chord_key), key) {
+                (Some(modifier), _) => {
+                    if !modifiers.contains(&modifier) {
+                        modifiers.push(modifier)
+                    }
+                }
+                (None, None) => key = Some(chord_key),
+                (None, Some(key)) => return Err(ChordError::MultipleKeys(key, chord_key)),
+            }
+        }
+        let key = key.ok_or(ChordError::NoKey)?;
+        Ok(Hotkey::new(modifiers, key))
+    }
+
+    pub fn with_repeat(mut self, repeat: bool) -> Hotkey {
+        self.no_repeat = !repeat;
+        self
+    }
+
+    /// Flags to pass to `RegisterHotKey`
+    /// ```
+    /// # use kemuler_windows::{VirtualKey, hotkey::*};
+    /// # use windows::Win32::UI::Input::KeyboardAndMouse::*;
+    /// let hotkey = Hotkey::new([Modifier::Control, Modifier::Alt], VirtualKey::Delete);
+    /// assert_eq!(hotkey.modifier_flags(), MOD_CONTROL | MOD_ALT | MOD_NOREPEAT);
+    /// assert_eq!(hotkey.with_repeat(true).modifier_flags(), MOD_CONTROL | MOD_ALT);
+    /// ```
+    pub fn modifier_flags(&self) -> HOT_KEY_MODIFIERS {
+        let no_repeat = if self.no_repeat {
+            KeyboardAndMouse::MOD_NOREPEAT
+        } else {
+            HOT_KEY_MODIFIERS(0)
+        };
+        self.modifiers
+            .iter()
+            .fold(no_repeat, |flags, modifier| flags | modifier.flag())
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{}+", modifier)?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// Global hotkeys registered on their own thread.
+///
+/// The hotkeys are unregistered when the manager is stopped or dropped.
+#[derive(Debug)]
+pub struct HotkeyManager {
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HotkeyManager {
+    /// Register the hotkeys and send every triggered hotkey to the returned receiver.
+    pub fn start(
+        hotkeys: impl IntoIterator<Item = Hotkey>,
+    ) -> windows::core::Result<(HotkeyManager, Receiver<Hotkey>)> {
+        let (sender, receiver) = mpsc::channel();
+        let manager = HotkeyManager::start_with_callback(hotkeys, move |hotkey| {
+            // the receiver may be dropped, the hotkeys stay registered regardless
+            let _ = sender.send(hotkey.clone());
+        })?;
+        Ok((manager, receiver))
+    }
+
+    /// Register the hotkeys and call `callback` on the manager thread
+    /// every time one of them is triggered.
+    pub fn start_with_callback(
+        hotkeys: impl IntoIterator<Item = Hotkey>,
+        mut callback: impl FnMut(&Hotkey) + Send + 'static,
+    ) -> windows::core::Result<HotkeyManager> {
+        let hotkeys = hotkeys.into_iter().collect::<Vec<_>>();
+        let (started_sender, started_receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            // hotkeys registered without a window are posted to the registering thread
+            for (id, hotkey) in hotkeys.iter().enumerate() {
+                let is_registered = unsafe {
+                    KeyboardAndMouse::RegisterHotKey(
+                        None,
+                        id as i32,
+                        hotkey.modifier_flags(),
+                        hotkey.key.code().0 as u32,
+                    )
+                };
+                if !is_registered.as_bool() {
+                    let _ = started_sender.send(Err(windows::core::Error::from_win32()));
+                    unregister_hotkeys(id);
+                    return;
+                }
+            }
+            // the id is only useful once `post_quit` can reach the thread
+            inner::message_loop::ensure_message_queue();
+            let _ = started_sender.send(Ok(unsafe { GetCurrentThreadId() }));
+
+            inner::message_loop::run_message_loop_with(|message| {
+                if message.message == WindowsAndMessaging::WM_HOTKEY {
+                    if let Some(hotkey) = hotkeys.get(message.wParam.0) {
+                        callback(hotkey);
+                    }
+                }
+            });
+            unregister_hotkeys(hotkeys.len());
+        });
+        let thread_id = started_receiver
+            .recv()
+            .expect("hotkey thread exited before registering hotkeys")?;
+        Ok(HotkeyManager {
+            thread_id,
+            thread: Some(thread),
+        })
+    }
+
+    /// Unregister the hotkeys and wait for the manager thread to exit.
+    pub fn stop(self) {
+        // `Drop` does the work
+    }
+}
+
+impl Drop for HotkeyManager {
+    fn drop(&mut self) {
+        // the thread never exits without `WM_QUIT`, joining it would hang
+        if inner::message_loop::post_quit(self.thread_id).is_err() {
+            return;
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Unregister hotkeys with id `0..count` of the current thread
+fn unregister_hotkeys(count: usize) {
+    for id in 0..count {
+        unsafe { KeyboardAndMouse::UnregisterHotKey(None, id as i32) };
+    }
+}
@@ -1,2 +1,8 @@
+/// Message loop helpers for threads that receive Windows messages
+pub mod message_loop;
+/// Synthetic touch and pen input
+pub mod pointer;
+/// Windows input state queries
+pub mod query;
 /// Windows `SendInput` solution
 pub mod send_input;
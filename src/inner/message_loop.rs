@@ -0,0 +1,54 @@
+use windows::Win32::{
+    Foundation::{LPARAM, WPARAM},
+    UI::WindowsAndMessaging,
+};
+
+/// Pump messages of the current thread until `WM_QUIT` is received
+pub fn run_message_loop() {
+    run_message_loop_with(|_| {});
+}
+
+/// Pump messages of the current thread until `WM_QUIT` is received,
+/// calling `on_message` with every message before it is dispatched.
+pub fn run_message_loop_with(mut on_message: impl FnMut(&WindowsAndMessaging::MSG)) {
+    let mut message = WindowsAndMessaging::MSG::default();
+    // `GetMessageW` return 0 on `WM_QUIT` and -1 on error
+    while unsafe { WindowsAndMessaging::GetMessageW(&mut message, None, 0, 0) }.0 > 0 {
+        on_message(&message);
+        unsafe {
+            WindowsAndMessaging::TranslateMessage(&message);
+            WindowsAndMessaging::DispatchMessageW(&message);
+        }
+    }
+}
+
+/// Create the message queue of the current thread.
+/// A thread has no queue until it calls a USER32 function,
+/// messages posted to it before that are lost.
+pub fn ensure_message_queue() {
+    let mut message = WindowsAndMessaging::MSG::default();
+    // SAFETY: peeking without removing leaves the queue as it is
+    unsafe {
+        WindowsAndMessaging::PeekMessageW(
+            &mut message,
+            None,
+            WindowsAndMessaging::WM_USER,
+            WindowsAndMessaging::WM_USER,
+            WindowsAndMessaging::PM_NOREMOVE,
+        )
+    };
+}
+
+/// Make the message loop of the thread exit
+pub fn post_quit(thread_id: u32) -> windows::core::Result<()> {
+    // SAFETY: posting `WM_QUIT` to a thread has no dangerous side-effects
+    unsafe {
+        WindowsAndMessaging::PostThreadMessageW(
+            thread_id,
+            WindowsAndMessaging::WM_QUIT,
+            WPARAM(0),
+            LPARAM(0),
+        )
+    }
+    .ok()
+}
@@ -0,0 +1,76 @@
+use std::sync::OnceLock;
+
+use windows::Win32::UI::{
+    Controls::{self, HSYNTHETICPOINTERDEVICE, POINTER_TYPE_INFO, POINTER_TYPE_INFO_0},
+    Input::Pointer::{self, POINTER_PEN_INFO, POINTER_TOUCH_INFO},
+    WindowsAndMessaging,
+};
+
+/// Maximum number of simultaneous touch contacts requested from `InitializeTouchInjection`
+pub const MAX_TOUCH_CONTACTS: u32 = 10;
+
+/// Call `InitializeTouchInjection` once per process.
+/// Later calls return the result of the first one.
+fn initialize_touch_injection() -> windows::core::Result<()> {
+    static INITIALIZED: OnceLock<windows::core::Result<()>> = OnceLock::new();
+    INITIALIZED
+        .get_or_init(|| {
+            let is_initialized = unsafe {
+                Pointer::InitializeTouchInjection(
+                    MAX_TOUCH_CONTACTS,
+                    Pointer::TOUCH_FEEDBACK_DEFAULT,
+                )
+            };
+            if is_initialized.as_bool() {
+                Ok(())
+            } else {
+                Err(windows::core::Error::from_win32())
+            }
+        })
+        .clone()
+}
+
+/// Inject one touch frame, initializing touch injection if needed.
+pub fn inject_touch_input(contacts: &[POINTER_TOUCH_INFO]) -> windows::core::Result<()> {
+    initialize_touch_injection()?;
+    let is_injected = unsafe { Pointer::InjectTouchInput(contacts) };
+    if is_injected.as_bool() {
+        Ok(())
+    } else {
+        Err(windows::core::Error::from_win32())
+    }
+}
+
+/// Synthetic pen device shared by the whole process, created on first use.
+fn pen_device() -> windows::core::Result<HSYNTHETICPOINTERDEVICE> {
+    // the handle is stored as `isize` because `HSYNTHETICPOINTERDEVICE` is not `Clone`
+    static DEVICE: OnceLock<windows::core::Result<isize>> = OnceLock::new();
+    DEVICE
+        .get_or_init(|| {
+            unsafe {
+                Controls::CreateSyntheticPointerDevice(
+                    WindowsAndMessaging::PT_PEN,
+                    1,
+                    Controls::POINTER_FEEDBACK_DEFAULT,
+                )
+            }
+            .map(|device| device.0)
+        })
+        .clone()
+        .map(HSYNTHETICPOINTERDEVICE)
+}
+
+/// Inject the state of the pen, creating the synthetic pen device if needed.
+pub fn inject_pen_input(pen_info: POINTER_PEN_INFO) -> windows::core::Result<()> {
+    let device = pen_device()?;
+    let pointer_info = POINTER_TYPE_INFO {
+        r#type: WindowsAndMessaging::PT_PEN,
+        Anonymous: POINTER_TYPE_INFO_0 { penInfo: pen_info },
+    };
+    let is_injected = unsafe { Pointer::InjectSyntheticPointerInput(device, &[pointer_info]) };
+    if is_injected.as_bool() {
+        Ok(())
+    } else {
+        Err(windows::core::Error::from_win32())
+    }
+}
@@ -0,0 +1,44 @@
+use windows::{
+    self,
+    Win32::UI::{Input::KeyboardAndMouse, WindowsAndMessaging},
+};
+
+use crate::VirtualKey;
+
+pub fn get_cursor_position() -> Option<(i32, i32)> {
+    let mut pos = windows::Win32::Foundation::POINT { x: 0, y: 0 };
+    let res = unsafe { WindowsAndMessaging::GetCursorPos(&mut pos) };
+    if res.as_bool() {
+        Some((pos.x, pos.y))
+    } else {
+        None
+    }
+}
+
+/// Raw `SHORT` returned by `GetAsyncKeyState`
+pub fn async_key_state(key: VirtualKey) -> i16 {
+    // SAFETY: calls has no dangerous side-effects
+    unsafe { KeyboardAndMouse::GetAsyncKeyState(key.code().0 as i32) }
+}
+
+/// Raw `SHORT` returned by `GetKeyState`.
+///
+/// `GetKeyState` reads the key state of the calling thread, which only follows the system
+/// when the thread processes input messages.
+/// `GetKeyboardState` is called first to bring it up to date on threads without a message loop.
+pub fn key_state(key: VirtualKey) -> i16 {
+    let mut keyboard_state = [0; 256];
+    // SAFETY: calls has no dangerous side-effects
+    unsafe {
+        KeyboardAndMouse::GetKeyboardState(&mut keyboard_state);
+        KeyboardAndMouse::GetKeyState(key.code().0 as i32)
+    }
+}
+
+/// Scan code of the key in the current keyboard layout, 0 if there is none
+pub fn scan_code(key: VirtualKey) -> u32 {
+    // SAFETY: calls has no dangerous side-effects
+    unsafe {
+        KeyboardAndMouse::MapVirtualKeyW(key.code().0 as u32, KeyboardAndMouse::MAPVK_VK_TO_VSC)
+    }
+}
@@ -7,12 +7,14 @@ use windows::{
     },
 };
 
+use super::query::get_cursor_position;
 use crate::{MouseButton, VirtualKey};
 
 // Thanks solution from https://stackoverflow.com/questions/35138778/sending-keys-to-a-directx-game
 
 #[allow(non_snake_case)]
-enum WindowsSendInputEnum {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowsSendInputEnum {
     Keyboard {
         wVk: KeyboardAndMouse::VIRTUAL_KEY,
         wScan: u16,
@@ -24,7 +26,6 @@ enum WindowsSendInputEnum {
         mouseData: i32,
         dwFlags: KeyboardAndMouse::MOUSE_EVENT_FLAGS,
     },
-    #[allow(unused)]
     Hardware {
         uMsg: u32,
         wParamL: u16,
@@ -33,7 +34,7 @@ enum WindowsSendInputEnum {
 }
 
 impl WindowsSendInputEnum {
-    pub fn into_windows(self) -> KeyboardAndMouse::INPUT {
+    pub fn into_windows(self, extra_info: usize) -> KeyboardAndMouse::INPUT {
         let (a, b) = match self {
             WindowsSendInputEnum::Keyboard {
                 wVk,
@@ -47,7 +48,7 @@ impl WindowsSendInputEnum {
                         wScan,
                         dwFlags,
                         time: 0,
-                        dwExtraInfo: get_message_extra_info(),
+                        dwExtraInfo: extra_info,
                     },
                 },
             ),
@@ -65,7 +66,7 @@ impl WindowsSendInputEnum {
                         mouseData,
                         dwFlags,
                         time: 0,
-                        dwExtraInfo: get_message_extra_info(),
+                        dwExtraInfo: extra_info,
                     },
                 },
             ),
@@ -99,22 +100,15 @@ fn primary_screen_size() -> (i32, i32) {
     (x, y)
 }
 
-fn virtual_screen_size() -> (i32, i32) {
+/// Position and size of the virtual desktop.
+/// The position is negative if a monitor is left of or above the primary monitor.
+pub fn virtual_screen_rect() -> ((i32, i32), (i32, i32)) {
     // SAFETY: calls has no dangerous side-effects
-    let x = unsafe { GetSystemMetrics(WindowsAndMessaging::SM_CXVIRTUALSCREEN) };
-    let y = unsafe { GetSystemMetrics(WindowsAndMessaging::SM_CYVIRTUALSCREEN) };
-    (x, y)
-}
-
-#[allow(unused)]
-fn get_cursor_position() -> Option<(i32, i32)> {
-    let mut pos = windows::Win32::Foundation::POINT { x: 0, y: 0 };
-    let res = unsafe { WindowsAndMessaging::GetCursorPos(&mut pos) };
-    if res.as_bool() {
-        Some((pos.x, pos.y))
-    } else {
-        None
-    }
+    let x = unsafe { GetSystemMetrics(WindowsAndMessaging::SM_XVIRTUALSCREEN) };
+    let y = unsafe { GetSystemMetrics(WindowsAndMessaging::SM_YVIRTUALSCREEN) };
+    let width = unsafe { GetSystemMetrics(WindowsAndMessaging::SM_CXVIRTUALSCREEN) };
+    let height = unsafe { GetSystemMetrics(WindowsAndMessaging::SM_CYVIRTUALSCREEN) };
+    ((x, y), (width, height))
 }
 
 #[allow(unused)]
@@ -122,36 +116,39 @@ fn ass_set_cursor_position(x: i32, y: i32) {
     unsafe { WindowsAndMessaging::SetCursorPos(x, y) };
 }
 
-fn send_input(inputs: &[KeyboardAndMouse::INPUT]) {
-    unsafe { KeyboardAndMouse::SendInput(inputs, size_of::<KeyboardAndMouse::INPUT>() as i32) };
+/// Return the number of events that were successfully inserted into the input stream
+pub fn send_input(inputs: &[WindowsSendInputEnum], extra_info: usize) -> u32 {
+    let inputs = inputs
+        .iter()
+        .map(|input| input.into_windows(extra_info))
+        .collect::<Vec<_>>();
+    unsafe { KeyboardAndMouse::SendInput(&inputs, size_of::<KeyboardAndMouse::INPUT>() as i32) }
 }
 
-fn get_message_extra_info() -> usize {
+pub fn get_message_extra_info() -> usize {
     unsafe { WindowsAndMessaging::GetMessageExtraInfo() }.0 as usize
 }
 
 // TODO: Needed testing
-pub fn mouse_scroll(x: i32, y: i32) {
-    send_input(&[
+pub fn mouse_scroll(x: i32, y: i32) -> Vec<WindowsSendInputEnum> {
+    vec![
         WindowsSendInputEnum::Mouse {
             dx: 0,
             dy: 0,
             mouseData: y,
             dwFlags: KeyboardAndMouse::MOUSEEVENTF_WHEEL,
-        }
-        .into_windows(),
+        },
         WindowsSendInputEnum::Mouse {
             dx: 0,
             dy: 0,
             mouseData: x,
             dwFlags: KeyboardAndMouse::MOUSEEVENTF_HWHEEL,
-        }
-        .into_windows(),
-    ]);
+        },
+    ]
 }
 
 // TODO: Needed testing
-pub fn mouse_button_down(button: MouseButton) {
+pub fn mouse_button_down(button: MouseButton) -> Vec<WindowsSendInputEnum> {
     let flag = match button {
         MouseButton::Left => KeyboardAndMouse::MOUSEEVENTF_LEFTDOWN,
         MouseButton::Middle => KeyboardAndMouse::MOUSEEVENTF_MIDDLEDOWN,
@@ -163,17 +160,16 @@ pub fn mouse_button_down(button: MouseButton) {
         MouseButton::X2 => WindowsAndMessaging::XBUTTON2,
         MouseButton::Left | MouseButton::Middle | MouseButton::Right => 0,
     };
-    send_input(&[WindowsSendInputEnum::Mouse {
+    vec![WindowsSendInputEnum::Mouse {
         dx: 0,
         dy: 0,
         mouseData: mouse_data as i32,
         dwFlags: flag,
-    }
-    .into_windows()]);
+    }]
 }
 
 // TODO: Needed testing
-pub fn mouse_button_up(button: MouseButton) {
+pub fn mouse_button_up(button: MouseButton) -> Vec<WindowsSendInputEnum> {
     let flag = match button {
         MouseButton::Left => KeyboardAndMouse::MOUSEEVENTF_LEFTUP,
         MouseButton::Middle => KeyboardAndMouse::MOUSEEVENTF_MIDDLEUP,
@@ -185,13 +181,12 @@ pub fn mouse_button_up(button: MouseButton) {
         MouseButton::X2 => WindowsAndMessaging::XBUTTON2,
         MouseButton::Left | MouseButton::Middle | MouseButton::Right => 0,
     };
-    send_input(&[WindowsSendInputEnum::Mouse {
+    vec![WindowsSendInputEnum::Mouse {
         dx: 0,
         dy: 0,
         mouseData: mouse_data as i32,
         dwFlags: flag,
-    }
-    .into_windows()]);
+    }]
 }
 
 /// `x` and `y` contain normalized absolute coordinates between 0 and 65,535.
@@ -200,44 +195,43 @@ pub fn mouse_button_up(button: MouseButton) {
 /// coordinate (65535,65535) maps onto the lower-right corner.
 /// In a multimonitor system, the coordinates map to the primary monitor.
 #[allow(unused)]
-pub fn mouse_move_to(x: i32, y: i32) {
-    send_input(&[WindowsSendInputEnum::Mouse {
+pub fn mouse_move_to(x: i32, y: i32) -> Vec<WindowsSendInputEnum> {
+    vec![WindowsSendInputEnum::Mouse {
         dx: x,
         dy: y,
         mouseData: 0,
         dwFlags: KeyboardAndMouse::MOUSEEVENTF_MOVE | KeyboardAndMouse::MOUSEEVENTF_ABSOLUTE,
-    }
-    .into_windows()]);
+    }]
 }
 
 // TODO: Needed testing
 #[allow(unused)]
-pub fn denormalized_mouse_move_to(x: i32, y: i32) {
+pub fn denormalized_mouse_move_to(x: i32, y: i32) -> Vec<WindowsSendInputEnum> {
     let (screen_size_x, screen_size_y) = primary_screen_size();
     let x = x * 65535 / screen_size_x;
     let y = y * 65535 / screen_size_y;
-    mouse_move_to(x, y);
+    mouse_move_to(x, y)
 }
 
 /// same as [`mouse_move_to`] but the coordinates map to the entire virtual desktop.
-pub fn virtual_desktop_mouse_move_to(x: i32, y: i32) {
-    send_input(&[WindowsSendInputEnum::Mouse {
+pub fn virtual_desktop_mouse_move_to(x: i32, y: i32) -> Vec<WindowsSendInputEnum> {
+    vec![WindowsSendInputEnum::Mouse {
         dx: x,
         dy: y,
         mouseData: 0,
         dwFlags: KeyboardAndMouse::MOUSEEVENTF_MOVE
             | KeyboardAndMouse::MOUSEEVENTF_ABSOLUTE
             | KeyboardAndMouse::MOUSEEVENTF_VIRTUALDESK,
-    }
-    .into_windows()]);
+    }]
 }
 
 // TODO: Needed testing
-pub fn virtual_desktop_denormalized_mouse_move_to(x: i32, y: i32) {
-    let (screen_size_x, screen_size_y) = virtual_screen_size();
-    let x = x * 65535 / screen_size_x;
-    let y = y * 65535 / screen_size_y;
-    virtual_desktop_mouse_move_to(x, y);
+/// Move to a position in virtual desktop pixels, which are negative left of and above the primary monitor
+pub fn virtual_desktop_denormalized_mouse_move_to(x: i32, y: i32) -> Vec<WindowsSendInputEnum> {
+    let ((screen_x, screen_y), (screen_size_x, screen_size_y)) = virtual_screen_rect();
+    let x = (x - screen_x) * 65535 / screen_size_x;
+    let y = (y - screen_y) * 65535 / screen_size_y;
+    virtual_desktop_mouse_move_to(x, y)
 }
 
 /// `x` and `y` specify movement relative to the previous mouse event (the last reported position).
@@ -260,107 +254,94 @@ pub fn virtual_desktop_denormalized_mouse_move_to(x: i32, y: i32) {
 /// from https://stackoverflow.com/questions/60268940/sendinput-mouse-movement-calculation
 /// It is not worth it trying to normalize by mathing.
 #[allow(unused)]
-pub fn mouse_move_by(x: i32, y: i32) {
-    send_input(&[WindowsSendInputEnum::Mouse {
+pub fn mouse_move_by(x: i32, y: i32) -> Vec<WindowsSendInputEnum> {
+    vec![WindowsSendInputEnum::Mouse {
         dx: x,
         dy: y,
         mouseData: 0,
         dwFlags: KeyboardAndMouse::MOUSEEVENTF_MOVE,
-    }
-    .into_windows()]);
+    }]
 }
 
-/// return true if is successful
+/// return `None` if the cursor position cannot be retrieved
 #[allow(unused)]
-pub fn deaccelerated_mouse_move_by(x: i32, y: i32) -> bool {
-    let Some((current_x, current_y)) = get_cursor_position() else {
-        return false;
-    };
-    denormalized_mouse_move_to(current_x + x, current_y + y);
-    true
+pub fn deaccelerated_mouse_move_by(x: i32, y: i32) -> Option<Vec<WindowsSendInputEnum>> {
+    let (current_x, current_y) = get_cursor_position()?;
+    Some(denormalized_mouse_move_to(current_x + x, current_y + y))
 }
 
-/// return true if is successful
+/// return `None` if the cursor position cannot be retrieved
 #[allow(unused)]
-pub fn virtual_desktop_deaccelerated_mouse_move_by(x: i32, y: i32) -> bool {
+pub fn virtual_desktop_deaccelerated_mouse_move_by(
+    x: i32,
+    y: i32,
+) -> Option<Vec<WindowsSendInputEnum>> {
     // i'm not sure about this one
-    let Some((current_x, current_y)) = get_cursor_position() else {
-        return false;
-    };
-    virtual_desktop_denormalized_mouse_move_to(current_x + x, current_y + y);
-    true
+    let (current_x, current_y) = get_cursor_position()?;
+    Some(virtual_desktop_denormalized_mouse_move_to(
+        current_x + x,
+        current_y + y,
+    ))
 }
 
-pub fn virtual_key_down(key: VirtualKey) {
-    send_input(&[WindowsSendInputEnum::Keyboard {
+pub fn virtual_key_down(key: VirtualKey) -> Vec<WindowsSendInputEnum> {
+    vec![WindowsSendInputEnum::Keyboard {
         wVk: key.code(),
         wScan: 0,
         dwFlags: KeyboardAndMouse::KEYBD_EVENT_FLAGS::default(),
-    }
-    .into_windows()]);
+    }]
 }
 
-pub fn virtual_key_up(key: VirtualKey) {
-    send_input(&[WindowsSendInputEnum::Keyboard {
+pub fn virtual_key_up(key: VirtualKey) -> Vec<WindowsSendInputEnum> {
+    vec![WindowsSendInputEnum::Keyboard {
         wVk: key.code(),
         wScan: 0,
         dwFlags: KeyboardAndMouse::KEYEVENTF_KEYUP,
-    }
-    .into_windows()]);
+    }]
 }
 
 #[allow(unused)]
-pub fn unicode_utf16_key_down(utf16_char: u16) {
-    send_input(&[WindowsSendInputEnum::Keyboard {
+pub fn unicode_utf16_key_down(utf16_char: u16) -> Vec<WindowsSendInputEnum> {
+    vec![WindowsSendInputEnum::Keyboard {
         wVk: KeyboardAndMouse::VIRTUAL_KEY(0),
         wScan: utf16_char,
         dwFlags: KeyboardAndMouse::KEYEVENTF_UNICODE,
-    }
-    .into_windows()]);
+    }]
 }
 
 #[allow(unused)]
-pub fn unicode_utf16_key_up(utf16_char: u16) {
-    send_input(&[WindowsSendInputEnum::Keyboard {
+pub fn unicode_utf16_key_up(utf16_char: u16) -> Vec<WindowsSendInputEnum> {
+    vec![WindowsSendInputEnum::Keyboard {
         wVk: KeyboardAndMouse::VIRTUAL_KEY(0),
         wScan: utf16_char,
         dwFlags: KeyboardAndMouse::KEYEVENTF_UNICODE | KeyboardAndMouse::KEYEVENTF_KEYUP,
-    }
-    .into_windows()]);
+    }]
 }
 
-pub fn char_key_down(char: char) {
+pub fn char_key_down(char: char) -> Vec<WindowsSendInputEnum> {
     // from the doc: A buffer of length 2 is large enough to encode any `char`.
     let mut utf16_bytes = [0; 2];
     let utf16_bytes = char.encode_utf16(&mut utf16_bytes);
-    let inputs = utf16_bytes
+    utf16_bytes
         .iter()
-        .map(|c| {
-            WindowsSendInputEnum::Keyboard {
-                wVk: KeyboardAndMouse::VIRTUAL_KEY(0),
-                wScan: *c,
-                dwFlags: KeyboardAndMouse::KEYEVENTF_UNICODE,
-            }
-            .into_windows()
+        .map(|c| WindowsSendInputEnum::Keyboard {
+            wVk: KeyboardAndMouse::VIRTUAL_KEY(0),
+            wScan: *c,
+            dwFlags: KeyboardAndMouse::KEYEVENTF_UNICODE,
         })
-        .collect::<Vec<_>>();
-    send_input(&inputs[..]);
+        .collect()
 }
 
-pub fn char_key_up(char: char) {
+pub fn char_key_up(char: char) -> Vec<WindowsSendInputEnum> {
     // from the doc: A buffer of length 2 is large enough to encode any `char`.
     let mut utf16_bytes = [0; 2];
     let utf16_bytes = char.encode_utf16(&mut utf16_bytes);
-    let inputs = utf16_bytes
+    utf16_bytes
         .iter()
-        .map(|c| {
-            WindowsSendInputEnum::Keyboard {
-                wVk: KeyboardAndMouse::VIRTUAL_KEY(0),
-                wScan: *c,
-                dwFlags: KeyboardAndMouse::KEYEVENTF_UNICODE | KeyboardAndMouse::KEYEVENTF_KEYUP,
-            }
-            .into_windows()
+        .map(|c| WindowsSendInputEnum::Keyboard {
+            wVk: KeyboardAndMouse::VIRTUAL_KEY(0),
+            wScan: *c,
+            dwFlags: KeyboardAndMouse::KEYEVENTF_UNICODE | KeyboardAndMouse::KEYEVENTF_KEYUP,
         })
-        .collect::<Vec<_>>();
-    send_input(&inputs[..]);
+        .collect()
 }
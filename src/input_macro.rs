@@ -0,0 +1,474 @@
+//! Record and replay input with its timing.
+//!
+//! [`Recorder`] captures real keyboard and mouse input with low-level hooks into a [`Macro`],
+//! and [`Player`] replays a [`Macro`] through any simulator, usually [`Windows`](crate::Windows).
+//!
+//! [`Macro`] is itself a simulator that records every simulated event,
+//! so macros can also be built with kemuler's combinators
+//! and playback can be observed without sending any input.
+//! It keeps only the order of events, [`MacroSink`] also records when they were simulated.
+//! ```
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{VirtualKey, input_macro::*};
+//! let mut recorded = Macro::new();
+//! recorded.simulate(VirtualKey::A.down());
+//! recorded.simulate(VirtualKey::A.up());
+//!
+//! let mut played = Macro::new();
+//! Player::new().play(&recorded, &mut played);
+//! assert_eq!(played, recorded);
+//! ```
+
+use std::{thread, time::Duration};
+
+use kemuler::{
+    common_inputs,
+    input_event::{ChangeBy, SetTo},
+    simulator::Simulate,
+};
+
+use crate::{
+    listener::{InputEvent, InputEventKind, Listener},
+    timing::{Clock, SystemClock},
+    MouseButton, VirtualKey,
+};
+
+/// An event in a [`Macro`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MacroEvent {
+    Key {
+        key: VirtualKey,
+        is_down: bool,
+    },
+    Char {
+        char: char,
+        is_down: bool,
+    },
+    MouseButton {
+        button: MouseButton,
+        is_down: bool,
+    },
+    /// Move the mouse to a position in virtual desktop pixels
+    MouseMove {
+        position: (i32, i32),
+    },
+    MouseScroll {
+        delta: (i32, i32),
+    },
+}
+
+impl MacroEvent {
+    /// Convert an observed input event.
+    /// Return `None` for keys unknown to [`VirtualKey`].
+    pub fn from_input_event(event: &InputEventKind) -> Option<MacroEvent> {
+        Some(match *event {
+            InputEventKind::Key { key, is_down, .. } => MacroEvent::Key { key: key?, is_down },
+            InputEventKind::MouseMove { position } => MacroEvent::MouseMove { position },
+            InputEventKind::MouseButton {
+                button, is_down, ..
+            } => MacroEvent::MouseButton { button, is_down },
+            InputEventKind::MouseScroll { delta, .. } => MacroEvent::MouseScroll { delta },
+        })
+    }
+
+    /// Simulate the event on `simulator`
+    pub fn simulate_with<S>(self, simulator: &mut S)
+    where
+        S: Simulate<SetTo<VirtualKey, bool>>
+            + Simulate<SetTo<common_inputs::Char, bool>>
+            + Simulate<SetTo<MouseButton, bool>>
+            + Simulate<SetTo<common_inputs::MousePosition, (i32, i32)>>
+            + Simulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>>,
+    {
+        match self {
+            MacroEvent::Key { key, is_down } => simulator.simulate(SetTo::new(key, is_down)),
+            MacroEvent::Char { char, is_down } => {
+                simulator.simulate(SetTo::new(common_inputs::Char(char), is_down))
+            }
+            MacroEvent::MouseButton { button, is_down } => {
+                simulator.simulate(SetTo::new(button, is_down))
+            }
+            MacroEvent::MouseMove { position } => {
+                simulator.simulate(SetTo::new(common_inputs::MousePosition, position))
+            }
+            MacroEvent::MouseScroll { delta } => {
+                simulator.simulate(ChangeBy::new(common_inputs::MouseScroll, delta))
+            }
+        }
+    }
+}
+
+/// A [`MacroEvent`] with the time it happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimedEvent {
+    /// Time since the start of the macro
+    pub time: Duration,
+    pub event: MacroEvent,
+}
+
+/// Sequence of input events with their timing.
+///
+/// Simulating an event on a macro appends it
+/// at the same time as the last event.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Macro {
+    pub events: Vec<TimedEvent>,
+}
+
+impl Macro {
+    pub fn new() -> Macro {
+        Macro::default()
+    }
+
+    /// Build a macro from observed input events.
+    /// Time stamps are made relative to the first event.
+    /// ```
+    /// # use kemuler_windows::{listener::*, input_macro::*};
+    /// # use std::time::Duration;
+    /// let event = |time, position| InputEvent {
+    ///     kind: InputEventKind::MouseMove { position },
+    ///     is_injected: false,
+    ///     time,
+    ///     extra_info: 0,
+    /// };
+    /// let recorded = Macro::from_input_events([event(1000, (0, 0)), event(1250, (5, 5))]);
+    /// assert_eq!(recorded.events[0].time, Duration::ZERO);
+    /// assert_eq!(recorded.events[1].time, Duration::from_millis(250));
+    /// ```
+    pub fn from_input_events(events: impl IntoIterator<Item = InputEvent>) -> Macro {
+        let mut start = None;
+        let events = events
+            .into_iter()
+            .filter_map(|input_event| {
+                let event = MacroEvent::from_input_event(&input_event.kind)?;
+                let start = *start.get_or_insert(input_event.time);
+                // the tick count wraps around every 49.7 days
+                let elapsed = input_event.time.wrapping_sub(start);
+                Some(TimedEvent {
+                    time: Duration::from_millis(elapsed as u64),
+                    event,
+                })
+            })
+            .collect();
+        Macro { events }
+    }
+
+    /// Time of the last event
+    pub fn duration(&self) -> Duration {
+        self.events
+            .last()
+            .map(|event| event.time)
+            .unwrap_or_default()
+    }
+
+    /// Append an event at the time of the last event.
+    /// The [`Simulate`] implementations of [`Macro`] use this so they keep only the order of events,
+    /// use a [`MacroSink`] to record when they happen.
+    pub fn push(&mut self, event: MacroEvent) {
+        let time = self.duration();
+        self.events.push(TimedEvent { time, event });
+    }
+}
+
+impl Simulate<SetTo<VirtualKey, bool>> for Macro {
+    fn simulate(&mut self, simulatable: SetTo<VirtualKey, bool>) {
+        let SetTo { input: key, to } = simulatable;
+        self.push(MacroEvent::Key { key, is_down: to })
+    }
+}
+
+impl Simulate<SetTo<common_inputs::Char, bool>> for Macro {
+    fn simulate(&mut self, simulatable: SetTo<common_inputs::Char, bool>) {
+        let SetTo { input: char, to } = simulatable;
+        self.push(MacroEvent::Char {
+            char: char.0,
+            is_down: to,
+        })
+    }
+}
+
+impl Simulate<SetTo<MouseButton, bool>> for Macro {
+    fn simulate(&mut self, simulatable: SetTo<MouseButton, bool>) {
+        let SetTo { input: button, to } = simulatable;
+        self.push(MacroEvent::MouseButton {
+            button,
+            is_down: to,
+        })
+    }
+}
+
+impl Simulate<SetTo<common_inputs::MousePosition, (i32, i32)>> for Macro {
+    fn simulate(&mut self, simulatable: SetTo<common_inputs::MousePosition, (i32, i32)>) {
+        let SetTo { input: _, to } = simulatable;
+        self.push(MacroEvent::MouseMove { position: to })
+    }
+}
+
+impl Simulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>> for Macro {
+    fn simulate(&mut self, simulatable: ChangeBy<common_inputs::MouseScroll, (i32, i32)>) {
+        let ChangeBy { input: _, by } = simulatable;
+        self.push(MacroEvent::MouseScroll { delta: by })
+    }
+}
+
+/// Simulator that records every event into a [`Macro`] with the time it was simulated at.
+/// ```
+/// # use std::{sync::Arc, time::Duration};
+/// # use kemuler_windows::{VirtualKey, input_macro::*, timing::{Clock, ManualClock}};
+/// let recorded = Macro {
+///     events: vec![
+///         TimedEvent { time: Duration::ZERO, event: MacroEvent::Key { key: VirtualKey::A, is_down: true } },
+///         TimedEvent { time: Duration::from_millis(100), event: MacroEvent::Key { key: VirtualKey::A, is_down: false } },
+///     ],
+/// };
+/// let clock = Arc::new(ManualClock::new());
+/// let mut sink = MacroSink::with_clock(clock.clone());
+/// Player::new().play_with_sleep(&recorded, &mut sink, |delay| clock.sleep(delay));
+/// assert_eq!(sink.into_macro(), recorded);
+/// ```
+#[derive(Debug, Default)]
+pub struct MacroSink<C = SystemClock> {
+    recorded: Macro,
+    clock: C,
+    start: Duration,
+}
+
+impl MacroSink {
+    pub fn new() -> MacroSink {
+        MacroSink::default()
+    }
+}
+
+impl<C: Clock> MacroSink<C> {
+    /// Time events with `clock`, starting now
+    pub fn with_clock(clock: C) -> MacroSink<C> {
+        MacroSink {
+            recorded: Macro::new(),
+            start: clock.now(),
+            clock,
+        }
+    }
+
+    pub fn recorded(&self) -> &Macro {
+        &self.recorded
+    }
+
+    pub fn into_macro(self) -> Macro {
+        self.recorded
+    }
+}
+
+impl<C, S> Simulate<S> for MacroSink<C>
+where
+    C: Clock,
+    Macro: Simulate<S>,
+{
+    fn simulate(&mut self, simulatable: S) {
+        let time = self.clock.now().saturating_sub(self.start);
+        let recorded = self.recorded.events.len();
+        self.recorded.simulate(simulatable);
+        for event in &mut self.recorded.events[recorded..] {
+            event.time = time;
+        }
+    }
+}
+
+/// Record real keyboard and mouse input into a [`Macro`].
+///
+/// Injected input, including input sent by this crate, is not recorded.
+/// ```no_run
+/// # use kemuler_windows::input_macro::Recorder;
+/// let recorder = Recorder::start().unwrap();
+/// std::thread::sleep(std::time::Duration::from_secs(5));
+/// let recorded = recorder.stop();
+/// ```
+#[derive(Debug)]
+pub struct Recorder {
+    listener: Listener,
+    events: std::sync::mpsc::Receiver<InputEvent>,
+}
+
+impl Recorder {
+    pub fn start() -> windows::core::Result<Recorder> {
+        let (listener, events) = Listener::start()?;
+        Ok(Recorder { listener, events })
+    }
+
+    /// Stop recording and return everything recorded so far.
+    pub fn stop(self) -> Macro {
+        self.listener.stop();
+        Macro::from_input_events(self.events.try_iter().filter(|event| !event.is_injected))
+    }
+}
+
+/// How many times a [`Player`] plays a macro
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Repeat {
+    Times(u32),
+    Forever,
+}
+
+/// Replay a [`Macro`] through a simulator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Player {
+    speed: f64,
+    repeat: Repeat,
+    max_idle: Option<Duration>,
+    skip_mouse_moves: bool,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Player {
+            speed: 1.0,
+            repeat: Repeat::Times(1),
+            max_idle: None,
+            skip_mouse_moves: false,
+        }
+    }
+}
+
+impl Player {
+    /// Slowest speed accepted by [`Player::with_speed`]
+    pub const MIN_SPEED: f64 = 0.001;
+    /// Fastest speed accepted by [`Player::with_speed`]
+    pub const MAX_SPEED: f64 = 1000.0;
+
+    pub fn new() -> Player {
+        Player::default()
+    }
+
+    /// Play `speed` times faster, e.g. `2.0` halves every delay.
+    /// `speed` is clamped to [`Player::MIN_SPEED`]..=[`Player::MAX_SPEED`].
+    /// ```
+    /// # use kemuler_windows::input_macro::Player;
+    /// assert_eq!(Player::new().with_speed(f64::INFINITY), Player::new().with_speed(Player::MAX_SPEED));
+    /// assert_eq!(Player::new().with_speed(1e-300), Player::new().with_speed(Player::MIN_SPEED));
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if `speed` is not positive or is NaN.
+    pub fn with_speed(mut self, speed: f64) -> Player {
+        assert!(speed > 0.0, "speed must be positive, got {speed}");
+        self.speed = speed.clamp(Player::MIN_SPEED, Player::MAX_SPEED);
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Player {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Shorten every gap between events that is longer than `max_idle` to `max_idle`.
+    /// This is applied before the speed multiplier.
+    pub fn with_max_idle(mut self, max_idle: Duration) -> Player {
+        self.max_idle = Some(max_idle);
+        self
+    }
+
+    /// Do not play mouse moves.
+    /// The time between the remaining events is kept.
+    pub fn without_mouse_moves(mut self) -> Player {
+        self.skip_mouse_moves = true;
+        self
+    }
+
+    /// Every event to play with the delay to wait before playing it.
+    /// This is endless for [`Repeat::Forever`].
+    /// ```
+    /// # use kemuler_windows::{VirtualKey, input_macro::*};
+    /// # use std::time::Duration;
+    /// let event = |millis, event| TimedEvent {
+    ///     time: Duration::from_millis(millis),
+    ///     event,
+    /// };
+    /// let recorded = Macro {
+    ///     events: vec![
+    ///         event(0, MacroEvent::Key { key: VirtualKey::A, is_down: true }),
+    ///         event(100, MacroEvent::MouseMove { position: (0, 0) }),
+    ///         event(5000, MacroEvent::Key { key: VirtualKey::A, is_down: false }),
+    ///     ],
+    /// };
+    /// let player = Player::new()
+    ///     .with_speed(2.0)
+    ///     .with_max_idle(Duration::from_secs(1))
+    ///     .without_mouse_moves()
+    ///     .with_repeat(Repeat::Times(2));
+    /// let delays = player
+    ///     .schedule(&recorded)
+    ///     .map(|(delay, _)| delay.as_millis())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(delays, [0, 500, 0, 500]);
+    /// ```
+    pub fn schedule<'a>(
+        &'a self,
+        recorded: &'a Macro,
+    ) -> impl Iterator<Item = (Duration, MacroEvent)> + 'a {
+        let times = match self.repeat {
+            Repeat::Times(times) => Some(times as usize),
+            Repeat::Forever => None,
+        };
+        let once = move || {
+            let mut previous = None;
+            recorded
+                .events
+                .iter()
+                .filter(move |timed| {
+                    !(self.skip_mouse_moves && matches!(timed.event, MacroEvent::MouseMove { .. }))
+                })
+                .map(move |timed| {
+                    let gap = previous
+                        .replace(timed.time)
+                        .map(|previous| timed.time.saturating_sub(previous))
+                        .unwrap_or_default();
+                    let gap = match self.max_idle {
+                        Some(max_idle) => gap.min(max_idle),
+                        None => gap,
+                    };
+                    let delay = Duration::try_from_secs_f64(gap.as_secs_f64() / self.speed)
+                        .unwrap_or(Duration::MAX);
+                    (delay, timed.event)
+                })
+        };
+        std::iter::repeat_with(once)
+            .take(times.unwrap_or(usize::MAX))
+            .flatten()
+    }
+
+    /// Play the macro, blocking the current thread.
+    /// This never returns for [`Repeat::Forever`].
+    pub fn play<S>(&self, recorded: &Macro, simulator: &mut S)
+    where
+        S: Simulate<SetTo<VirtualKey, bool>>
+            + Simulate<SetTo<common_inputs::Char, bool>>
+            + Simulate<SetTo<MouseButton, bool>>
+            + Simulate<SetTo<common_inputs::MousePosition, (i32, i32)>>
+            + Simulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>>,
+    {
+        self.play_with_sleep(recorded, simulator, thread::sleep)
+    }
+
+    /// Play the macro, calling `sleep` for the delay before each event.
+    pub fn play_with_sleep<S>(
+        &self,
+        recorded: &Macro,
+        simulator: &mut S,
+        mut sleep: impl FnMut(Duration),
+    ) where
+        S: Simulate<SetTo<VirtualKey, bool>>
+            + Simulate<SetTo<common_inputs::Char, bool>>
+            + Simulate<SetTo<MouseButton, bool>>
+            + Simulate<SetTo<common_inputs::MousePosition, (i32, i32)>>
+            + Simulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>>,
+    {
+        for (delay, event) in self.schedule(recorded) {
+            if !delay.is_zero() {
+                sleep(delay);
+            }
+            event.simulate_with(simulator);
+        }
+    }
+}
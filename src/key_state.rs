@@ -0,0 +1,35 @@
+/// State of a key as reported by Windows.
+///
+/// See [`Windows::key_state`](crate::Windows::key_state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct KeyState {
+    /// The key is currently held down
+    pub is_down: bool,
+    /// The key is toggled on.
+    /// Only meaningful for toggle keys like CapsLock, NumLock and ScrollLock
+    pub is_toggled: bool,
+}
+
+impl KeyState {
+    /// Decode the `SHORT`s returned by `GetAsyncKeyState` and `GetKeyState`.
+    ///
+    /// The most significant bit of `GetAsyncKeyState` tells if the key is down
+    /// and the least significant bit of `GetKeyState` tells if the key is toggled.
+    /// ```
+    /// # use kemuler_windows::KeyState;
+    /// let state = KeyState::from_raw(i16::MIN, 0x0001);
+    /// assert!(state.is_down);
+    /// assert!(state.is_toggled);
+    ///
+    /// // the "pressed since last call" bit is ignored
+    /// let state = KeyState::from_raw(0x0001, 0);
+    /// assert!(!state.is_down);
+    /// assert!(!state.is_toggled);
+    /// ```
+    pub fn from_raw(async_key_state: i16, key_state: i16) -> KeyState {
+        KeyState {
+            is_down: async_key_state < 0,
+            is_toggled: key_state & 0x0001 != 0,
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Emergency stop for running automation.
+//!
+//! A [`KillSwitch`] set on [`Windows`](crate::Windows) is checked before every batch of input.
+//! Once one of its [`Trigger`]s fires or it is tripped from another thread,
+//! every key and button held by the simulator is released right away, even while the simulator is waiting,
+//! and every following simulation returns [`Error::KillSwitchTripped`](crate::Error::KillSwitchTripped).
+//! ```
+//! # use kemuler::prelude::*;
+//! # use kemuler_windows::{VirtualKey, Windows, dry_run::Recording, kill_switch::KillSwitch};
+//! let kill_switch = KillSwitch::new([]);
+//! let log = Recording::new();
+//! let mut windows = Windows::new()
+//!     .with_signature(0)
+//!     .with_dry_run(log.clone())
+//!     .with_kill_switch(kill_switch.clone());
+//! windows.simulate(VirtualKey::LShift.down());
+//! // e.g. from a hotkey while a script waits
+//! kill_switch.trip();
+//! assert!(log.lines()[1].starts_with("KEYUP VK_LSHIFT"));
+//! ```
+//! The pen is lifted the next time the simulator checks the switch.
+//! ```no_run
+//! # use kemuler_windows::{VirtualKey, Windows, TrySimulate, kill_switch::*};
+//! // stop when the cursor is moved into a screen corner or Ctrl+Alt+Pause is held
+//! let kill_switch = KillSwitch::new([
+//!     Trigger::CursorInCorner { margin: 0 },
+//!     Trigger::KeysDown(vec![VirtualKey::Control, VirtualKey::Alt, VirtualKey::Pause]),
+//! ]);
+//! let mut windows = Windows::new().with_kill_switch(kill_switch);
+//! while windows.try_simulate(VirtualKey::Space.down()).is_ok()
+//!     && windows.try_simulate(VirtualKey::Space.up()).is_ok()
+//! {}
+//! ```
+//!
+//! Triggers are polled when input is sent, so a short key press between two simulations
+//! can be missed. [`KillSwitch::register_hotkey`] trips the switch from a global hotkey instead.
+//!
+//! Input paced by a [`Timing`](crate::timing::Timing) checks the switch before every event,
+//! so a long sequence stops in the middle.
+//! ```
+//! # use std::{sync::Arc, time::Duration};
+//! # use kemuler::{common_inputs::MouseScroll, input_event::ChangeBy};
+//! # use kemuler_windows::{Error, TrySimulate, VirtualKey, Windows, dry_run::Recording, kill_switch::*, timing::*};
+//! /// Pause is pressed 5ms from now
+//! struct PauseLater(Arc<ManualClock>);
+//!
+//! impl StateSource for PauseLater {
+//!     fn cursor_position(&self) -> Option<(i32, i32)> { None }
+//!     fn is_key_down(&self, key: VirtualKey) -> bool {
+//!         key == VirtualKey::Pause && self.0.now() >= Duration::from_millis(5)
+//!     }
+//!     fn screen_rect(&self) -> ((i32, i32), (i32, i32)) { ((0, 0), (1920, 1080)) }
+//! }
+//!
+//! let clock = Arc::new(ManualClock::new());
+//! let kill_switch = KillSwitch::new([Trigger::KeysDown(vec![VirtualKey::Pause])])
+//!     .with_state(PauseLater(clock.clone()));
+//! let log = Recording::new();
+//! let mut windows = Windows::new()
+//!     .with_signature(0)
+//!     .with_dry_run(log.clone())
+//!     .with_clock(clock.clone())
+//!     .with_timing(Timing::new().with_between_events(Duration::from_millis(10)))
+//!     .with_kill_switch(kill_switch);
+//! // a vertical and a horizontal wheel event, 10ms apart
+//! let result = windows.try_simulate(ChangeBy::new(MouseScroll, (120, 120)));
+//! assert_eq!(result, Err(Error::KillSwitchTripped));
+//! assert_eq!(log.lines().len(), 1);
+//! ```
+//! The simulator does not hold its send lock while it waits,
+//! so tripping the switch from another thread releases held input right away.
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use crate::{
+    hotkey::{Hotkey, HotkeyManager},
+    inner, Error, KeyState, VirtualKey,
+};
+
+/// Source of the input state checked by [`Trigger`]s.
+///
+/// [`SystemState`] asks Windows, other implementations are useful for testing.
+pub trait StateSource: Send + Sync {
+    /// Cursor position in virtual desktop pixels
+    fn cursor_position(&self) -> Option<(i32, i32)>;
+    fn is_key_down(&self, key: VirtualKey) -> bool;
+    /// Position and size of the virtual desktop
+    fn screen_rect(&self) -> ((i32, i32), (i32, i32));
+}
+
+/// Query the input state from Windows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SystemState;
+
+impl StateSource for SystemState {
+    fn cursor_position(&self) -> Option<(i32, i32)> {
+        inner::query::get_cursor_position()
+    }
+
+    fn is_key_down(&self, key: VirtualKey) -> bool {
+        KeyState::from_raw(
+            inner::query::async_key_state(key),
+            inner::query::key_state(key),
+        )
+        .is_down
+    }
+
+    fn screen_rect(&self) -> ((i32, i32), (i32, i32)) {
+        inner::send_input::virtual_screen_rect()
+    }
+}
+
+/// Condition that trips a [`KillSwitch`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    /// The cursor is within `margin` pixels of a corner of the virtual desktop
+    CursorInCorner { margin: i32 },
+    /// All keys are held down at the same time
+    KeysDown(Vec<VirtualKey>),
+}
+
+impl Trigger {
+    /// Return true if the trigger fires in `state`.
+    /// ```
+    /// # use kemuler_windows::{VirtualKey, kill_switch::*};
+    /// struct FakeState {
+    ///     cursor: (i32, i32),
+    ///     keys_down: Vec<VirtualKey>,
+    /// }
+    ///
+    /// impl StateSource for FakeState {
+    ///     fn cursor_position(&self) -> Option<(i32, i32)> {
+    ///         Some(self.cursor)
+    ///     }
+    ///     fn is_key_down(&self, key: VirtualKey) -> bool {
+    ///         self.keys_down.contains(&key)
+    ///     }
+    ///     fn screen_rect(&self) -> ((i32, i32), (i32, i32)) {
+    ///         ((-1920, 0), (3840, 1080))
+    ///     }
+    /// }
+    ///
+    /// let corner = Trigger::CursorInCorner { margin: 2 };
+    /// let state = FakeState { cursor: (-1919, 1), keys_down: vec![] };
+    /// assert!(corner.is_triggered(&state));
+    /// let state = FakeState { cursor: (1917, 1079), keys_down: vec![] };
+    /// assert!(corner.is_triggered(&state));
+    /// let state = FakeState { cursor: (0, 0), keys_down: vec![VirtualKey::Pause] };
+    /// assert!(!corner.is_triggered(&state));
+    ///
+    /// let keys = Trigger::KeysDown(vec![VirtualKey::Control, VirtualKey::Pause]);
+    /// assert!(!keys.is_triggered(&state));
+    /// let state = FakeState { cursor: (0, 0), keys_down: vec![VirtualKey::Control, VirtualKey::Pause] };
+    /// assert!(keys.is_triggered(&state));
+    /// ```
+    pub fn is_triggered(&self, state: &dyn StateSource) -> bool {
+        match self {
+            Trigger::CursorInCorner { margin } => {
+                let Some((x, y)) = state.cursor_position() else {
+                    return false;
+                };
+                let ((left, top), (width, height)) = state.screen_rect();
+                let (right, bottom) = (left + width - 1, top + height - 1);
+                let is_at_x = (x - left).abs() <= *margin || (right - x).abs() <= *margin;
+                let is_at_y = (y - top).abs() <= *margin || (bottom - y).abs() <= *margin;
+                is_at_x && is_at_y
+            }
+            Trigger::KeysDown(keys) => {
+                !keys.is_empty() && keys.iter().all(|&key| state.is_key_down(key))
+            }
+        }
+    }
+}
+
+/// Callbacks called when a [`KillSwitch`] trips
+#[derive(Default)]
+struct OnTrip {
+    next_id: u64,
+    callbacks: Vec<(u64, Arc<dyn Fn() + Send + Sync>)>,
+}
+
+/// Stops all input once tripped.
+///
+/// Clones share whether the switch is tripped.
+#[derive(Clone)]
+pub struct KillSwitch {
+    triggers: Vec<Trigger>,
+    state: Arc<dyn StateSource>,
+    is_tripped: Arc<AtomicBool>,
+    on_trip: Arc<Mutex<OnTrip>>,
+}
+
+impl fmt::Debug for KillSwitch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KillSwitch")
+            .field("triggers", &self.triggers)
+            .field("is_tripped", &self.is_tripped())
+            .finish_non_exhaustive()
+    }
+}
+
+impl KillSwitch {
+    pub fn new(triggers: impl IntoIterator<Item = Trigger>) -> KillSwitch {
+        KillSwitch {
+            triggers: triggers.into_iter().collect(),
+            state: Arc::new(SystemState),
+            is_tripped: Arc::new(AtomicBool::new(false)),
+            on_trip: Arc::default(),
+        }
+    }
+
+    /// Trip when the cursor is moved into a corner of the screen
+    pub fn failsafe() -> KillSwitch {
+        KillSwitch::new([Trigger::CursorInCorner { margin: 0 }])
+    }
+
+    pub fn with_state(mut self, state: impl StateSource + 'static) -> KillSwitch {
+        self.state = Arc::new(state);
+        self
+    }
+
+    pub fn triggers(&self) -> &[Trigger] {
+        &self.triggers
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.is_tripped.load(Ordering::SeqCst)
+    }
+
+    /// Trip the switch, every following check fails.
+    /// Simulators using the switch release what they hold right away.
+    pub fn trip(&self) {
+        if self.is_tripped.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // callbacks may take other locks, do not hold this one while calling them
+        let callbacks = self
+            .on_trip()
+            .callbacks
+            .iter()
+            .map(|(_, callback)| Arc::clone(callback))
+            .collect::<Vec<_>>();
+        for callback in callbacks {
+            callback();
+        }
+    }
+
+    /// Call `callback` every time the switch trips, return an id for [`KillSwitch::remove_on_trip`]
+    pub(crate) fn add_on_trip(&self, callback: impl Fn() + Send + Sync + 'static) -> u64 {
+        let mut on_trip = self.on_trip();
+        let id = on_trip.next_id;
+        on_trip.next_id += 1;
+        on_trip.callbacks.push((id, Arc::new(callback)));
+        id
+    }
+
+    pub(crate) fn remove_on_trip(&self, id: u64) {
+        self.on_trip()
+            .callbacks
+            .retain(|(callback_id, _)| *callback_id != id);
+    }
+
+    fn on_trip(&self) -> std::sync::MutexGuard<'_, OnTrip> {
+        self.on_trip.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Allow input again after the switch was tripped.
+    pub fn reset(&self) {
+        self.is_tripped.store(false, Ordering::SeqCst);
+    }
+
+    /// Trip the switch when `hotkey` is pressed, for as long as the returned manager lives.
+    pub fn register_hotkey(&self, hotkey: Hotkey) -> windows::core::Result<HotkeyManager> {
+        let kill_switch = self.clone();
+        HotkeyManager::start_with_callback([hotkey], move |_| kill_switch.trip())
+    }
+
+    /// Check the triggers and trip the switch if one fires.
+    /// Return an error if the switch is tripped.
+    /// ```
+    /// # use kemuler_windows::{Error, VirtualKey, kill_switch::*};
+    /// # struct NothingDown;
+    /// # impl StateSource for NothingDown {
+    /// #     fn cursor_position(&self) -> Option<(i32, i32)> { Some((100, 100)) }
+    /// #     fn is_key_down(&self, _: VirtualKey) -> bool { false }
+    /// #     fn screen_rect(&self) -> ((i32, i32), (i32, i32)) { ((0, 0), (1920, 1080)) }
+    /// # }
+    /// let kill_switch = KillSwitch::failsafe().with_state(NothingDown);
+    /// assert_eq!(kill_switch.check(), Ok(()));
+    /// kill_switch.trip();
+    /// assert_eq!(kill_switch.check(), Err(Error::KillSwitchTripped));
+    /// kill_switch.reset();
+    /// assert_eq!(kill_switch.check(), Ok(()));
+    /// ```
+    pub fn check(&self) -> Result<(), Error> {
+        if !self.is_tripped()
+            && self
+                .triggers
+                .iter()
+                .any(|trigger| trigger.is_triggered(&*self.state))
+        {
+            self.trip();
+        }
+        if self.is_tripped() {
+            Err(Error::KillSwitchTripped)
+        } else {
+            Ok(())
+        }
+    }
+}
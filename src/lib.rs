@@ -1,4 +1,7 @@
-use std::fmt;
+use std::{
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
 
 use kemuler::{
     common_inputs,
@@ -6,10 +9,6 @@ use kemuler::{
     simulator::Simulate,
 };
 
-mod inner;
-mod virtual_key;
-pub use virtual_key::VirtualKey;
-
 macro_rules! button_like_impl_body {
     () => {
         /// Set this button state
@@ -74,6 +73,41 @@ macro_rules! button_like_impl_body {
     };
 }
 
+pub mod ahk;
+pub mod asynchronous;
+pub mod cadence;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod dry_run;
+mod error;
+mod extra_info;
+pub mod guard;
+mod hardware_input;
+pub mod hotkey;
+mod inner;
+pub mod input_macro;
+mod key_state;
+pub mod kill_switch;
+pub mod listener;
+pub mod pen;
+pub mod script;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod shared;
+pub mod timing;
+pub mod touch;
+#[cfg(feature = "tracing")]
+mod trace;
+mod virtual_key;
+pub mod window;
+pub mod window_target;
+pub mod xdotool;
+pub use error::Error;
+pub use extra_info::ExtraInfo;
+pub use hardware_input::{HardwareInput, HardwareInputError};
+pub use key_state::KeyState;
+pub use virtual_key::VirtualKey;
+
 impl VirtualKey {
     button_like_impl_body! {}
 }
@@ -85,6 +119,7 @@ impl fmt::Display for VirtualKey {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[non_exhaustive]
 pub enum MouseButton {
     Left,
@@ -100,6 +135,17 @@ pub enum MouseButton {
 
 impl MouseButton {
     button_like_impl_body! {}
+
+    /// Virtual key that represent this button
+    pub fn virtual_key(self) -> VirtualKey {
+        match self {
+            MouseButton::Left => VirtualKey::LButton,
+            MouseButton::Middle => VirtualKey::MButton,
+            MouseButton::Right => VirtualKey::RButton,
+            MouseButton::X1 => VirtualKey::XButton1,
+            MouseButton::X2 => VirtualKey::XButton2,
+        }
+    }
 }
 
 impl fmt::Display for MouseButton {
@@ -108,6 +154,72 @@ impl fmt::Display for MouseButton {
     }
 }
 
+/// Lock keys whose toggle state can be set directly.
+///
+/// Simulating `SetTo<ToggleKey, bool>` sets the lock state rather than the physical key state:
+/// the key is only clicked if its current toggle state differs from the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ToggleKey {
+    CapsLock,
+    NumLock,
+    ScrollLock,
+}
+
+impl ToggleKey {
+    /// Set this lock state
+    /// This is a convenience shorthand for
+    /// ```
+    /// # use kemuler::input_event::*;
+    /// # let this = 0i32;
+    /// # let to = 0i32;
+    /// SetTo { input: this, to: to }
+    /// # ;
+    /// ```
+    pub fn set_to(self, to: bool) -> kemuler::input_event::SetTo<Self, bool> {
+        kemuler::input_event::SetTo::new(self, to)
+    }
+
+    /// Turn the lock on.
+    /// This is a convenience shorthand for
+    /// ```
+    /// # use kemuler::input_event::*;
+    /// # let this = 0i32;
+    /// SetTo { input: this, to: true }
+    /// # ;
+    /// ```
+    pub fn on(self) -> kemuler::input_event::SetTo<Self, bool> {
+        self.set_to(true)
+    }
+
+    /// Turn the lock off.
+    /// This is a convenience shorthand for
+    /// ```
+    /// # use kemuler::input_event::*;
+    /// # let this = 0i32;
+    /// SetTo { input: this, to: false }
+    /// # ;
+    /// ```
+    pub fn off(self) -> kemuler::input_event::SetTo<Self, bool> {
+        self.set_to(false)
+    }
+
+    /// Virtual key that represent this lock
+    pub fn virtual_key(self) -> VirtualKey {
+        match self {
+            ToggleKey::CapsLock => VirtualKey::CapsLock,
+            ToggleKey::NumLock => VirtualKey::NumLock,
+            ToggleKey::ScrollLock => VirtualKey::ScrollLock,
+        }
+    }
+}
+
+impl fmt::Display for ToggleKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 fn windowsify_common_mouse_button(button: common_inputs::MouseButton) -> MouseButton {
     match button {
         common_inputs::MouseButton::Left => MouseButton::Left,
@@ -153,94 +265,834 @@ fn windowsify_common_key(key: common_inputs::Key) -> VirtualKey {
     }
 }
 
-#[derive(Default)]
-pub struct Windows;
+/// Simulator that sends input with `SendInput`.
+///
+/// Every [`Simulate`] implementation has a [`TrySimulate`] counterpart
+/// that returns an error instead of panicking when input is refused.
+#[derive(Debug, Default)]
+pub struct Windows {
+    extra_info: ExtraInfo,
+    foreground_guard: Option<guard::ForegroundGuard>,
+    pen: pen::PenState,
+    pacer: timing::Pacer,
+    kill_switch: Option<kill_switch::KillSwitch>,
+    /// Id of the callback releasing held input when the kill switch trips
+    release_on_trip: Option<u64>,
+    /// Held while sending so the kill switch cannot release between tracking and injecting
+    send_lock: Arc<Mutex<()>>,
+    dry_run: Option<dry_run::DryRun>,
+}
+
+impl Clone for Windows {
+    fn clone(&self) -> Windows {
+        let mut windows = Windows {
+            extra_info: self.extra_info,
+            foreground_guard: self.foreground_guard.clone(),
+            pen: self.pen,
+            pacer: self.pacer.clone(),
+            kill_switch: self.kill_switch.clone(),
+            release_on_trip: None,
+            send_lock: Arc::default(),
+            dry_run: self.dry_run.clone(),
+        };
+        windows.register_release_on_trip();
+        windows
+    }
+}
+
+impl Drop for Windows {
+    fn drop(&mut self) {
+        if let (Some(kill_switch), Some(id)) = (&self.kill_switch, self.release_on_trip) {
+            kill_switch.remove_on_trip(id);
+        }
+    }
+}
 
 impl Windows {
     pub fn new() -> Windows {
-        Windows
+        Windows::default()
+    }
+
+    /// Set what to write into `dwExtraInfo` of every event.
+    pub fn with_extra_info(mut self, extra_info: ExtraInfo) -> Windows {
+        self.extra_info = extra_info;
+        self.register_release_on_trip();
+        self
+    }
+
+    /// Write `signature` into `dwExtraInfo` of every event.
+    /// This is a convenience shorthand for
+    /// `with_extra_info(ExtraInfo::Signature(signature))`
+    pub fn with_signature(self, signature: usize) -> Windows {
+        self.with_extra_info(ExtraInfo::Signature(signature))
+    }
+
+    pub fn extra_info(&self) -> ExtraInfo {
+        self.extra_info
+    }
+
+    /// Return true if an observed event's extra info matches this simulator's signature.
+    /// See [`ExtraInfo::matches`].
+    pub fn is_own_extra_info(&self, observed: usize) -> bool {
+        self.extra_info.matches(observed)
+    }
+
+    /// Refuse to send input when the guarded window is not in the foreground.
+    /// See [`guard`].
+    pub fn with_foreground_guard(mut self, guard: guard::ForegroundGuard) -> Windows {
+        self.foreground_guard = Some(guard);
+        self
+    }
+
+    pub fn without_foreground_guard(mut self) -> Windows {
+        self.foreground_guard = None;
+        self
+    }
+
+    pub fn foreground_guard(&self) -> Option<&guard::ForegroundGuard> {
+        self.foreground_guard.as_ref()
+    }
+
+    /// Describe every event to `sink` instead of sending it.
+    /// See [`dry_run`].
+    pub fn with_dry_run(mut self, sink: impl dry_run::DryRunSink + 'static) -> Windows {
+        self.dry_run = Some(dry_run::DryRun::new(sink));
+        self.register_release_on_trip();
+        self
+    }
+
+    pub fn without_dry_run(mut self) -> Windows {
+        self.dry_run = None;
+        self.register_release_on_trip();
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run.is_some()
+    }
+
+    /// Stop all input once `kill_switch` is tripped.
+    /// See [`kill_switch`].
+    ///
+    /// Held input is released as soon as the switch trips, from the tripping thread.
+    pub fn with_kill_switch(mut self, kill_switch: kill_switch::KillSwitch) -> Windows {
+        self = self.without_kill_switch();
+        self.kill_switch = Some(kill_switch);
+        self.register_release_on_trip();
+        self
+    }
+
+    pub fn without_kill_switch(mut self) -> Windows {
+        if let (Some(kill_switch), Some(id)) = (&self.kill_switch, self.release_on_trip.take()) {
+            kill_switch.remove_on_trip(id);
+        }
+        self.kill_switch = None;
+        self
+    }
+
+    /// Release held input from the thread tripping the kill switch,
+    /// replacing the previous callback so it sends with the current settings
+    fn register_release_on_trip(&mut self) {
+        let Some(kill_switch) = &self.kill_switch else {
+            return;
+        };
+        if let Some(id) = self.release_on_trip.take() {
+            kill_switch.remove_on_trip(id);
+        }
+        let held = self.pacer.held_handle();
+        let send_lock = Arc::clone(&self.send_lock);
+        let extra_info = self.extra_info;
+        let dry_run = self.dry_run.clone();
+        let id = kill_switch.add_on_trip(move || {
+            let _sending = send_lock.lock().unwrap_or_else(PoisonError::into_inner);
+            let inputs = held
+                .take()
+                .into_iter()
+                .flat_map(timing::Held::release)
+                .collect::<Vec<_>>();
+            if !inputs.is_empty() {
+                // best effort, there is nothing to do if the input is blocked
+                let _ = inject(dry_run.as_ref(), &inputs, extra_info_value(extra_info));
+            }
+        });
+        self.release_on_trip = Some(id);
+    }
+
+    pub fn kill_switch(&self) -> Option<&kill_switch::KillSwitch> {
+        self.kill_switch.as_ref()
+    }
+
+    /// Release every key and button this simulator is holding down and lift the pen.
+    /// Input is sent even if a guard or the kill switch would refuse it.
+    pub fn release_all(&mut self) {
+        let inputs = self
+            .pacer
+            .take_held()
+            .into_iter()
+            .flat_map(timing::Held::release)
+            .collect::<Vec<_>>();
+        if !inputs.is_empty() {
+            // best effort, there is nothing to do if the input is blocked
+            let _ = inject(self.dry_run.as_ref(), &inputs, self.extra_info_value());
+        }
+        if self.pen.is_in_contact {
+            let pen = self.pen.with_in_range(false);
+            // best effort, there is nothing to do if the pen cannot be lifted
+            let _ = self.inject_pen(pen.into_windows(&self.pen));
+            self.pen = pen;
+        }
+    }
+
+    /// Release what of `held` this simulator is still holding down, bypassing checks like
+    /// [`Windows::release_all`]
+    pub(crate) fn release(&mut self, held: impl IntoIterator<Item = timing::Held>) {
+        let inputs = held
+            .into_iter()
+            .filter(|&held| self.pacer.forget(held))
+            .flat_map(timing::Held::release)
+            .collect::<Vec<_>>();
+        if !inputs.is_empty() {
+            // best effort, there is nothing to do if the input is blocked
+            let _ = inject(self.dry_run.as_ref(), &inputs, self.extra_info_value());
+        }
+    }
+
+    /// Delay events and hold keys down according to `timing`.
+    /// See [`timing`].
+    pub fn with_timing(mut self, timing: timing::Timing) -> Windows {
+        self.pacer.set_timing(timing);
+        self
+    }
+
+    /// Read and wait on time through `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: impl timing::Clock + 'static) -> Windows {
+        self.pacer = std::mem::take(&mut self.pacer).with_clock(clock);
+        self
+    }
+
+    pub fn timing(&self) -> timing::Timing {
+        self.pacer.timing()
+    }
+
+    /// Current cursor position in virtual desktop pixels.
+    /// Return `None` if the position cannot be retrieved.
+    pub fn cursor_position(&self) -> Option<(i32, i32)> {
+        inner::query::get_cursor_position()
+    }
+
+    /// Current state of the key.
+    pub fn key_state(&self, key: VirtualKey) -> KeyState {
+        KeyState::from_raw(
+            inner::query::async_key_state(key),
+            inner::query::key_state(key),
+        )
+    }
+
+    /// Return true if the key is currently held down.
+    pub fn is_key_down(&self, key: VirtualKey) -> bool {
+        self.key_state(key).is_down
+    }
+
+    /// Return true if the key is toggled on.
+    /// Only meaningful for toggle keys like CapsLock, NumLock and ScrollLock
+    pub fn is_key_toggled(&self, key: VirtualKey) -> bool {
+        self.key_state(key).is_toggled
+    }
+
+    /// Return true if the lock is currently on.
+    pub fn is_toggle_key_on(&self, key: ToggleKey) -> bool {
+        self.is_key_toggled(key.virtual_key())
+    }
+
+    /// Return true if the mouse button is currently held down.
+    /// This checks the physical button, ignoring swapped left and right buttons.
+    pub fn is_mouse_button_down(&self, button: MouseButton) -> bool {
+        self.is_key_down(button.virtual_key())
+    }
+
+    /// Last injected state of the synthetic pen
+    pub fn pen_state(&self) -> pen::PenState {
+        self.pen
+    }
+
+    /// Value to write into `dwExtraInfo`
+    fn extra_info_value(&self) -> usize {
+        extra_info_value(self.extra_info)
+    }
+
+    /// Check everything that can refuse input before sending it
+    fn check_can_send(&mut self) -> Result<(), Error> {
+        if let Some(kill_switch) = &self.kill_switch {
+            if let Err(error) = kill_switch.check() {
+                self.release_all();
+                return Err(error);
+            }
+        }
+        if let Some(guard) = &self.foreground_guard {
+            guard.check()?;
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, inputs: &[inner::send_input::WindowsSendInputEnum]) -> Result<(), Error> {
+        let extra_info = self.extra_info_value();
+        let events = timing::PacedEvent::from_send_inputs(inputs);
+        if self.pacer.timing().is_zero() {
+            // nothing to wait for, send everything at once
+            return self.send_now(
+                events.into_iter().map(|(event, _)| event),
+                inputs,
+                extra_info,
+            );
+        }
+        let mut start = 0;
+        for (event, length) in events {
+            // without the send lock, so the kill switch can release while waiting
+            self.pacer.wait_until_ready(event);
+            // focus can move and the kill switch can trip while waiting
+            self.send_now([event], &inputs[start..start + length], extra_info)?;
+            start += length;
+        }
+        Ok(())
+    }
+
+    /// Check, record and inject `inputs` made of `events` right away.
+    /// The send lock is held so the kill switch cannot release between recording and injecting.
+    fn send_now(
+        &mut self,
+        events: impl IntoIterator<Item = timing::PacedEvent>,
+        inputs: &[inner::send_input::WindowsSendInputEnum],
+        extra_info: usize,
+    ) -> Result<(), Error> {
+        self.check_can_send()?;
+        let send_lock = Arc::clone(&self.send_lock);
+        let sending = send_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        // tripped from another thread after the check, its release may already be done
+        if self
+            .kill_switch
+            .as_ref()
+            .is_some_and(kill_switch::KillSwitch::is_tripped)
+        {
+            drop(sending);
+            self.release_all();
+            return Err(Error::KillSwitchTripped);
+        }
+        for event in events {
+            self.pacer.record(event);
+        }
+        inject(self.dry_run.as_ref(), inputs, extra_info)
+    }
+
+    fn send_touch(&mut self, frame: &touch::TouchFrame) -> Result<(), Error> {
+        frame.validate().map_err(Error::InvalidTouchFrame)?;
+        self.check_can_send()?;
+        let contacts = frame.into_windows();
+        match &self.dry_run {
+            Some(dry_run) => {
+                dry_run.log_touch(&contacts);
+                Ok(())
+            }
+            None => {
+                #[cfg(feature = "tracing")]
+                trace::touch(&contacts);
+                let result = inner::pointer::inject_touch_input(&contacts);
+                #[cfg(feature = "tracing")]
+                if let Err(error) = &result {
+                    trace::injection_error(error);
+                }
+                result.map_err(Error::Injection)
+            }
+        }
+    }
+
+    fn send_pen(&mut self, state: pen::PenState) -> Result<(), Error> {
+        state.validate().map_err(Error::InvalidPenState)?;
+        self.check_can_send()?;
+        self.inject_pen(state.into_windows(&self.pen))
+            .map_err(Error::Injection)?;
+        self.pen = state;
+        Ok(())
+    }
+
+    /// Inject the pen state, or describe it in dry-run mode.
+    /// Nothing is checked.
+    fn inject_pen(
+        &self,
+        pen: windows::Win32::UI::Input::Pointer::POINTER_PEN_INFO,
+    ) -> windows::core::Result<()> {
+        match &self.dry_run {
+            Some(dry_run) => {
+                dry_run.log_pen(&pen);
+                Ok(())
+            }
+            None => {
+                #[cfg(feature = "tracing")]
+                trace::pen(&pen);
+                let result = inner::pointer::inject_pen_input(pen);
+                #[cfg(feature = "tracing")]
+                if let Err(error) = &result {
+                    trace::injection_error(error);
+                }
+                result
+            }
+        }
     }
 }
 
-impl Simulate<SetTo<VirtualKey, bool>> for Windows {
-    fn simulate(&mut self, simulatable: SetTo<VirtualKey, bool>) {
+fn extra_info_value(extra_info: ExtraInfo) -> usize {
+    match extra_info {
+        ExtraInfo::MessageExtraInfo => inner::send_input::get_message_extra_info(),
+        ExtraInfo::Signature(signature) => signature,
+    }
+}
+
+/// Send input with `SendInput`, or describe it in dry-run mode.
+/// Nothing is checked.
+fn inject(
+    dry_run: Option<&dry_run::DryRun>,
+    inputs: &[inner::send_input::WindowsSendInputEnum],
+    extra_info: usize,
+) -> Result<(), Error> {
+    match dry_run {
+        Some(dry_run) => {
+            dry_run.log_inputs(inputs, extra_info);
+            Ok(())
+        }
+        None => {
+            #[cfg(feature = "tracing")]
+            for input in inputs {
+                trace::input(&input.into_windows(extra_info));
+            }
+            let inserted = inner::send_input::send_input(inputs, extra_info);
+            #[cfg(feature = "tracing")]
+            trace::send_input_result(inputs.len(), inserted);
+            // `SendInput` is blocked by UIPI or when a secure desktop is active
+            if inserted as usize != inputs.len() {
+                return Err(Error::Injection(windows::core::Error::from_win32()));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Fallible version of [`Simulate`].
+///
+/// [`Windows`] can refuse to send input, e.g. because of a [`ForegroundGuard`](guard::ForegroundGuard).
+/// Its [`Simulate`] implementations panic in that case,
+/// use this trait to handle the error instead.
+pub trait TrySimulate<S> {
+    fn try_simulate(&mut self, simulatable: S) -> Result<(), Error>;
+}
+
+/// Implement [`Simulate`] for a simulator by panicking on [`TrySimulate`] errors
+macro_rules! simulate_by_try_simulate {
+    ($simulator:ty; $($simulatable:ty),* $(,)?) => {
+        $(
+            impl Simulate<$simulatable> for $simulator {
+                fn simulate(&mut self, simulatable: $simulatable) {
+                    if let Err(error) = self.try_simulate(simulatable) {
+                        panic!("failed to simulate input: {}", error);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+pub(crate) use simulate_by_try_simulate;
+
+simulate_by_try_simulate! {
+    Windows;
+    SetTo<VirtualKey, bool>,
+    SetTo<ToggleKey, bool>,
+    SetTo<common_inputs::Key, bool>,
+    SetTo<common_inputs::Char, bool>,
+    SetTo<MouseButton, bool>,
+    SetTo<common_inputs::MouseButton, bool>,
+    SetTo<common_inputs::MousePosition, (i32, i32)>,
+    SetTo<window::WindowMousePosition, (i32, i32)>,
+    ChangeBy<common_inputs::MousePosition, (i32, i32)>,
+    ChangeBy<common_inputs::MouseScroll, (i32, i32)>,
+    HardwareInput,
+    cadence::Typing,
+    touch::TouchFrame,
+    touch::Gesture,
+    SetTo<pen::PenButton, bool>,
+    SetTo<pen::PenPosition, (i32, i32)>,
+    SetTo<pen::PenPressure, u32>,
+    SetTo<pen::PenTilt, (i32, i32)>,
+    SetTo<pen::PenRotation, u32>,
+    SetTo<pen::PenInRange, bool>,
+    pen::Stroke,
+    input_macro::MacroEvent,
+    script::Command,
+}
+
+impl<S> TrySimulate<timing::Timed<S>> for Windows
+where
+    Windows: TrySimulate<S>,
+{
+    fn try_simulate(&mut self, simulatable: timing::Timed<S>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "simulate",
+            input = std::any::type_name::<S>(),
+            timing = ?simulatable.timing
+        )
+        .entered();
+        let timing = self.pacer.timing();
+        self.pacer.set_timing(simulatable.timing);
+        let result = self.try_simulate(simulatable.simulatable);
+        self.pacer.set_timing(timing);
+        result
+    }
+}
+
+impl<S> Simulate<timing::Timed<S>> for Windows
+where
+    Windows: TrySimulate<S>,
+{
+    fn simulate(&mut self, simulatable: timing::Timed<S>) {
+        if let Err(error) = TrySimulate::<timing::Timed<S>>::try_simulate(self, simulatable) {
+            panic!("failed to simulate input: {}", error);
+        }
+    }
+}
+
+impl TrySimulate<SetTo<VirtualKey, bool>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<VirtualKey, bool>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
         let SetTo {
             input: key,
             to: is_down,
         } = simulatable;
         if is_down {
-            inner::send_input::virtual_key_down(key)
+            self.send(&inner::send_input::virtual_key_down(key))
         } else {
-            inner::send_input::virtual_key_up(key)
+            self.send(&inner::send_input::virtual_key_up(key))
+        }
+    }
+}
+
+impl TrySimulate<SetTo<ToggleKey, bool>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<ToggleKey, bool>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        let SetTo {
+            input: key,
+            to: is_on,
+        } = simulatable;
+        if self.is_toggle_key_on(key) != is_on {
+            let key = key.virtual_key();
+            // one send so the key cannot be refused between going down and up
+            let mut inputs = inner::send_input::virtual_key_down(key);
+            inputs.extend(inner::send_input::virtual_key_up(key));
+            self.send(&inputs)?;
         }
+        Ok(())
     }
 }
 
-impl Simulate<SetTo<common_inputs::Key, bool>> for Windows {
-    fn simulate(&mut self, simulatable: SetTo<common_inputs::Key, bool>) {
+impl TrySimulate<SetTo<common_inputs::Key, bool>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<common_inputs::Key, bool>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
         let SetTo {
             input: key,
             to: is_down,
         } = simulatable;
         let key = windowsify_common_key(key);
         if is_down {
-            inner::send_input::virtual_key_down(key)
+            self.send(&inner::send_input::virtual_key_down(key))
         } else {
-            inner::send_input::virtual_key_up(key)
+            self.send(&inner::send_input::virtual_key_up(key))
         }
     }
 }
 
-impl Simulate<SetTo<common_inputs::Char, bool>> for Windows {
-    fn simulate(&mut self, simulatable: SetTo<common_inputs::Char, bool>) {
+impl TrySimulate<SetTo<common_inputs::Char, bool>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<common_inputs::Char, bool>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
         let SetTo {
             input: char,
             to: is_down,
         } = simulatable;
         let char = char.0;
         if is_down {
-            inner::send_input::char_key_down(char)
+            self.send(&inner::send_input::char_key_down(char))
         } else {
-            inner::send_input::char_key_up(char)
+            self.send(&inner::send_input::char_key_up(char))
         }
     }
 }
 
-impl Simulate<SetTo<common_inputs::MouseButton, bool>> for Windows {
-    fn simulate(&mut self, simulatable: SetTo<common_inputs::MouseButton, bool>) {
+impl TrySimulate<SetTo<MouseButton, bool>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<MouseButton, bool>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
         let SetTo {
             input: button,
             to: is_down,
         } = simulatable;
-        let button = windowsify_common_mouse_button(button);
         if is_down {
-            inner::send_input::mouse_button_down(button)
+            self.send(&inner::send_input::mouse_button_down(button))
         } else {
-            inner::send_input::mouse_button_up(button)
+            self.send(&inner::send_input::mouse_button_up(button))
         }
     }
 }
 
-impl Simulate<SetTo<common_inputs::MousePosition, (i32, i32)>> for Windows {
-    fn simulate(&mut self, simulatable: SetTo<common_inputs::MousePosition, (i32, i32)>) {
+impl TrySimulate<SetTo<common_inputs::MouseButton, bool>> for Windows {
+    fn try_simulate(
+        &mut self,
+        simulatable: SetTo<common_inputs::MouseButton, bool>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        let SetTo {
+            input: button,
+            to: is_down,
+        } = simulatable;
+        self.try_simulate(SetTo::new(windowsify_common_mouse_button(button), is_down))
+    }
+}
+
+impl TrySimulate<SetTo<common_inputs::MousePosition, (i32, i32)>> for Windows {
+    fn try_simulate(
+        &mut self,
+        simulatable: SetTo<common_inputs::MousePosition, (i32, i32)>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
         let SetTo {
             input: _,
             to: position,
         } = simulatable;
-        inner::send_input::virtual_desktop_denormalized_mouse_move_to(position.0, position.1);
+        self.send(
+            &inner::send_input::virtual_desktop_denormalized_mouse_move_to(position.0, position.1),
+        )
     }
 }
 
-impl Simulate<ChangeBy<common_inputs::MousePosition, (i32, i32)>> for Windows {
-    fn simulate(&mut self, simulatable: ChangeBy<common_inputs::MousePosition, (i32, i32)>) {
+impl TrySimulate<SetTo<window::WindowMousePosition, (i32, i32)>> for Windows {
+    fn try_simulate(
+        &mut self,
+        simulatable: SetTo<window::WindowMousePosition, (i32, i32)>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        let SetTo {
+            input: position,
+            to: offset,
+        } = simulatable;
+        let (x, y) = position
+            .resolve(offset)
+            .ok_or(Error::WindowUnavailable(position.window))?;
+        self.send(&inner::send_input::virtual_desktop_denormalized_mouse_move_to(x, y))
+    }
+}
+
+impl TrySimulate<ChangeBy<common_inputs::MousePosition, (i32, i32)>> for Windows {
+    fn try_simulate(
+        &mut self,
+        simulatable: ChangeBy<common_inputs::MousePosition, (i32, i32)>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
         let ChangeBy { input: _, by } = simulatable;
-        inner::send_input::deaccelerated_mouse_move_by(by.0, by.1);
+        match inner::send_input::deaccelerated_mouse_move_by(by.0, by.1) {
+            Some(inputs) => self.send(&inputs),
+            None => Ok(()),
+        }
     }
 }
 
-impl Simulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>> for Windows {
-    fn simulate(&mut self, simulatable: ChangeBy<common_inputs::MouseScroll, (i32, i32)>) {
+impl TrySimulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>> for Windows {
+    fn try_simulate(
+        &mut self,
+        simulatable: ChangeBy<common_inputs::MouseScroll, (i32, i32)>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
         let ChangeBy { input: _, by } = simulatable;
-        inner::send_input::mouse_scroll(by.0, by.1)
+        self.send(&inner::send_input::mouse_scroll(by.0, by.1))
+    }
+}
+
+impl TrySimulate<HardwareInput> for Windows {
+    fn try_simulate(&mut self, simulatable: HardwareInput) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        self.send(&[simulatable.into_send_input()])
+    }
+}
+
+impl TrySimulate<cadence::Typing> for Windows {
+    fn try_simulate(&mut self, simulatable: cadence::Typing) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        for keystroke in simulatable.schedule() {
+            self.pacer.sleep(keystroke.delay);
+            let (down, up) = match keystroke.key {
+                cadence::TypedKey::Char(char) => (
+                    inner::send_input::char_key_down(char),
+                    inner::send_input::char_key_up(char),
+                ),
+                cadence::TypedKey::Backspace => (
+                    inner::send_input::virtual_key_down(VirtualKey::Backspace),
+                    inner::send_input::virtual_key_up(VirtualKey::Backspace),
+                ),
+            };
+            self.send(&down)?;
+            self.send(&up)?;
+        }
+        Ok(())
+    }
+}
+
+impl TrySimulate<touch::TouchFrame> for Windows {
+    fn try_simulate(&mut self, simulatable: touch::TouchFrame) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        self.send_touch(&simulatable)
+    }
+}
+
+impl TrySimulate<touch::Gesture> for Windows {
+    fn try_simulate(&mut self, simulatable: touch::Gesture) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        for (i, frame) in simulatable.frames().iter().enumerate() {
+            if i != 0 {
+                self.pacer.sleep(touch::FRAME_INTERVAL);
+            }
+            self.send_touch(frame)?;
+        }
+        Ok(())
+    }
+}
+
+impl TrySimulate<SetTo<pen::PenButton, bool>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<pen::PenButton, bool>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        let SetTo {
+            input: button,
+            to: is_down,
+        } = simulatable;
+        self.send_pen(self.pen.with_button(button, is_down))
+    }
+}
+
+impl TrySimulate<SetTo<pen::PenPosition, (i32, i32)>> for Windows {
+    fn try_simulate(
+        &mut self,
+        simulatable: SetTo<pen::PenPosition, (i32, i32)>,
+    ) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        self.send_pen(pen::PenState {
+            position: simulatable.to,
+            is_in_range: true,
+            ..self.pen
+        })
+    }
+}
+
+impl TrySimulate<SetTo<pen::PenPressure, u32>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<pen::PenPressure, u32>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        self.send_pen(pen::PenState {
+            pressure: simulatable.to,
+            is_in_range: true,
+            ..self.pen
+        })
+    }
+}
+
+impl TrySimulate<SetTo<pen::PenTilt, (i32, i32)>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<pen::PenTilt, (i32, i32)>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        self.send_pen(pen::PenState {
+            tilt: simulatable.to,
+            is_in_range: true,
+            ..self.pen
+        })
+    }
+}
+
+impl TrySimulate<SetTo<pen::PenRotation, u32>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<pen::PenRotation, u32>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        self.send_pen(pen::PenState {
+            rotation: simulatable.to,
+            is_in_range: true,
+            ..self.pen
+        })
+    }
+}
+
+impl TrySimulate<SetTo<pen::PenInRange, bool>> for Windows {
+    fn try_simulate(&mut self, simulatable: SetTo<pen::PenInRange, bool>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        self.send_pen(self.pen.with_in_range(simulatable.to))
+    }
+}
+
+impl TrySimulate<pen::Stroke> for Windows {
+    fn try_simulate(&mut self, simulatable: pen::Stroke) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        for (i, state) in simulatable.states().into_iter().enumerate() {
+            if i != 0 {
+                self.pacer.sleep(pen::FRAME_INTERVAL);
+            }
+            self.send_pen(state)?;
+        }
+        Ok(())
+    }
+}
+
+impl TrySimulate<input_macro::MacroEvent> for Windows {
+    fn try_simulate(&mut self, simulatable: input_macro::MacroEvent) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        use input_macro::MacroEvent;
+        match simulatable {
+            MacroEvent::Key { key, is_down } => self.try_simulate(SetTo::new(key, is_down)),
+            MacroEvent::Char { char, is_down } => {
+                self.try_simulate(SetTo::new(common_inputs::Char(char), is_down))
+            }
+            MacroEvent::MouseButton { button, is_down } => {
+                self.try_simulate(SetTo::new(button, is_down))
+            }
+            MacroEvent::MouseMove { position } => {
+                self.try_simulate(SetTo::new(common_inputs::MousePosition, position))
+            }
+            MacroEvent::MouseScroll { delta } => {
+                self.try_simulate(ChangeBy::new(common_inputs::MouseScroll, delta))
+            }
+        }
+    }
+}
+
+impl TrySimulate<script::Command> for Windows {
+    fn try_simulate(&mut self, simulatable: script::Command) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span = trace::simulate(&simulatable);
+        if let script::Command::Wait(duration) = simulatable {
+            self.pacer.sleep(duration);
+            return Ok(());
+        }
+        for event in simulatable.events() {
+            self.try_simulate(event)?;
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,320 @@
+//! Observe keyboard and mouse input with low-level hooks.
+//!
+//! [`Listener`] installs `WH_KEYBOARD_LL` and `WH_MOUSE_LL` hooks on a dedicated thread
+//! running a message loop, and delivers every observed event as an [`InputEvent`] over a channel.
+//! Events are only observed, they are always passed on to the next hook.
+//!
+//! The translation from raw hook data to typed events is done by
+//! [`translate_keyboard_event`] and [`translate_mouse_event`] which do not call into Windows.
+
+use std::{
+    cell::RefCell,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use windows::Win32::{
+    Foundation::{LPARAM, LRESULT, WPARAM},
+    System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
+    UI::WindowsAndMessaging::{self, KBDLLHOOKSTRUCT, MSLLHOOKSTRUCT},
+};
+
+use crate::{inner, MouseButton, VirtualKey};
+
+/// An observed input event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputEvent {
+    pub kind: InputEventKind,
+    /// The event was injected, e.g. by `SendInput`
+    pub is_injected: bool,
+    /// Time stamp in milliseconds, on the same clock as `GetTickCount`
+    pub time: u32,
+    /// `dwExtraInfo` of the event.
+    /// See [`ExtraInfo::matches`](crate::ExtraInfo::matches)
+    pub extra_info: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputEventKind {
+    Key {
+        /// `None` if the virtual-key code is not known by [`VirtualKey`]
+        key: Option<VirtualKey>,
+        /// Raw virtual-key code
+        code: u16,
+        scan_code: u32,
+        /// The key is an extended key, e.g. the right-hand ALT and CTRL keys
+        is_extended: bool,
+        is_down: bool,
+    },
+    MouseMove {
+        /// Position in virtual desktop pixels
+        position: (i32, i32),
+    },
+    MouseButton {
+        button: MouseButton,
+        is_down: bool,
+        /// Position in virtual desktop pixels
+        position: (i32, i32),
+    },
+    MouseScroll {
+        /// Wheel delta in the same unit as `WHEEL_DELTA`,
+        /// in the same order as [`common_inputs::MouseScroll`](kemuler::common_inputs::MouseScroll)
+        delta: (i32, i32),
+        /// Position in virtual desktop pixels
+        position: (i32, i32),
+    },
+}
+
+/// Translate the data received by a `WH_KEYBOARD_LL` hook into an [`InputEvent`].
+/// Return `None` if `message` is not a keyboard message.
+/// ```
+/// # use kemuler_windows::{VirtualKey, listener::*};
+/// # use windows::Win32::UI::WindowsAndMessaging::*;
+/// let hook = KBDLLHOOKSTRUCT {
+///     vkCode: 0x41,
+///     scanCode: 0x1E,
+///     flags: LLKHF_INJECTED,
+///     time: 1234,
+///     dwExtraInfo: 0,
+/// };
+/// let event = translate_keyboard_event(WM_KEYDOWN, &hook).unwrap();
+/// assert_eq!(
+///     event.kind,
+///     InputEventKind::Key {
+///         key: Some(VirtualKey::A),
+///         code: 0x41,
+///         scan_code: 0x1E,
+///         is_extended: false,
+///         is_down: true,
+///     }
+/// );
+/// assert!(event.is_injected);
+/// assert_eq!(event.time, 1234);
+/// assert_eq!(translate_keyboard_event(WM_MOUSEMOVE, &hook), None);
+/// ```
+pub fn translate_keyboard_event(message: u32, hook: &KBDLLHOOKSTRUCT) -> Option<InputEvent> {
+    let is_down = match message {
+        WindowsAndMessaging::WM_KEYDOWN | WindowsAndMessaging::WM_SYSKEYDOWN => true,
+        WindowsAndMessaging::WM_KEYUP | WindowsAndMessaging::WM_SYSKEYUP => false,
+        _ => return None,
+    };
+    let code = hook.vkCode as u16;
+    Some(InputEvent {
+        kind: InputEventKind::Key {
+            key: VirtualKey::from_code(code),
+            code,
+            scan_code: hook.scanCode,
+            is_extended: hook.flags.0 & WindowsAndMessaging::LLKHF_EXTENDED.0 != 0,
+            is_down,
+        },
+        is_injected: hook.flags.0 & WindowsAndMessaging::LLKHF_INJECTED.0 != 0,
+        time: hook.time,
+        extra_info: hook.dwExtraInfo,
+    })
+}
+
+/// Translate the data received by a `WH_MOUSE_LL` hook into an [`InputEvent`].
+/// Return `None` if `message` is not a mouse message this crate knows about.
+/// ```
+/// # use kemuler_windows::{MouseButton, listener::*};
+/// # use windows::Win32::{Foundation::POINT, UI::WindowsAndMessaging::*};
+/// let hook = MSLLHOOKSTRUCT {
+///     pt: POINT { x: 10, y: 20 },
+///     mouseData: (XBUTTON2 as u32) << 16,
+///     flags: 0,
+///     time: 1234,
+///     dwExtraInfo: 0,
+/// };
+/// let event = translate_mouse_event(WM_XBUTTONUP, &hook).unwrap();
+/// assert_eq!(
+///     event.kind,
+///     InputEventKind::MouseButton {
+///         button: MouseButton::X2,
+///         is_down: false,
+///         position: (10, 20),
+///     }
+/// );
+/// assert!(!event.is_injected);
+///
+/// let hook = MSLLHOOKSTRUCT {
+///     mouseData: (-120i16 as u16 as u32) << 16,
+///     ..hook
+/// };
+/// let event = translate_mouse_event(WM_MOUSEWHEEL, &hook).unwrap();
+/// assert_eq!(
+///     event.kind,
+///     InputEventKind::MouseScroll {
+///         delta: (0, -120),
+///         position: (10, 20),
+///     }
+/// );
+/// ```
+pub fn translate_mouse_event(message: u32, hook: &MSLLHOOKSTRUCT) -> Option<InputEvent> {
+    let position = (hook.pt.x, hook.pt.y);
+    // the high-order word of `mouseData` is the wheel delta or the X button
+    let high_word = (hook.mouseData >> 16) as u16;
+    let button = |button, is_down| InputEventKind::MouseButton {
+        button,
+        is_down,
+        position,
+    };
+    let kind = match message {
+        WindowsAndMessaging::WM_MOUSEMOVE => InputEventKind::MouseMove { position },
+        WindowsAndMessaging::WM_LBUTTONDOWN => button(MouseButton::Left, true),
+        WindowsAndMessaging::WM_LBUTTONUP => button(MouseButton::Left, false),
+        WindowsAndMessaging::WM_MBUTTONDOWN => button(MouseButton::Middle, true),
+        WindowsAndMessaging::WM_MBUTTONUP => button(MouseButton::Middle, false),
+        WindowsAndMessaging::WM_RBUTTONDOWN => button(MouseButton::Right, true),
+        WindowsAndMessaging::WM_RBUTTONUP => button(MouseButton::Right, false),
+        WindowsAndMessaging::WM_XBUTTONDOWN | WindowsAndMessaging::WM_XBUTTONUP => {
+            let is_down = message == WindowsAndMessaging::WM_XBUTTONDOWN;
+            match high_word {
+                WindowsAndMessaging::XBUTTON1 => button(MouseButton::X1, is_down),
+                WindowsAndMessaging::XBUTTON2 => button(MouseButton::X2, is_down),
+                _ => return None,
+            }
+        }
+        WindowsAndMessaging::WM_MOUSEWHEEL => InputEventKind::MouseScroll {
+            delta: (0, high_word as i16 as i32),
+            position,
+        },
+        WindowsAndMessaging::WM_MOUSEHWHEEL => InputEventKind::MouseScroll {
+            delta: (high_word as i16 as i32, 0),
+            position,
+        },
+        _ => return None,
+    };
+    Some(InputEvent {
+        kind,
+        is_injected: hook.flags & WindowsAndMessaging::LLMHF_INJECTED != 0,
+        time: hook.time,
+        extra_info: hook.dwExtraInfo,
+    })
+}
+
+thread_local! {
+    // Low-level hooks are called on the thread that installed them,
+    // and hook procedures have no user data, so the sender lives here.
+    static SENDER: RefCell<Option<Sender<InputEvent>>> = const { RefCell::new(None) };
+}
+
+fn deliver(event: Option<InputEvent>) {
+    let Some(event) = event else {
+        return;
+    };
+    SENDER.with(|sender| {
+        if let Some(sender) = &*sender.borrow() {
+            // the receiver may be dropped, we keep passing events on regardless
+            let _ = sender.send(event);
+        }
+    });
+}
+
+unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == WindowsAndMessaging::HC_ACTION as i32 {
+        // SAFETY: `lparam` points to a `KBDLLHOOKSTRUCT` for `WH_KEYBOARD_LL` hooks
+        let hook = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        deliver(translate_keyboard_event(wparam.0 as u32, hook));
+    }
+    WindowsAndMessaging::CallNextHookEx(None, code, wparam, lparam)
+}
+
+unsafe extern "system" fn mouse_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == WindowsAndMessaging::HC_ACTION as i32 {
+        // SAFETY: `lparam` points to a `MSLLHOOKSTRUCT` for `WH_MOUSE_LL` hooks
+        let hook = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+        deliver(translate_mouse_event(wparam.0 as u32, hook));
+    }
+    WindowsAndMessaging::CallNextHookEx(None, code, wparam, lparam)
+}
+
+/// Low-level keyboard and mouse hooks running on their own thread.
+///
+/// The hooks are removed when the listener is stopped or dropped.
+/// ```no_run
+/// # use kemuler_windows::listener::Listener;
+/// let (listener, events) = Listener::start().unwrap();
+/// for event in events.iter().take(10) {
+///     println!("{:?}", event);
+/// }
+/// listener.stop();
+/// ```
+#[derive(Debug)]
+pub struct Listener {
+    thread_id: u32,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Listener {
+    /// Install the hooks and start delivering events to the returned receiver.
+    pub fn start() -> windows::core::Result<(Listener, Receiver<InputEvent>)> {
+        let (event_sender, event_receiver) = mpsc::channel();
+        let (started_sender, started_receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            SENDER.with(|sender| *sender.borrow_mut() = Some(event_sender));
+            // SAFETY: hook procedures are valid for the lifetime of the program
+            let hooks = unsafe {
+                GetModuleHandleW(None).and_then(|module| {
+                    let keyboard = WindowsAndMessaging::SetWindowsHookExW(
+                        WindowsAndMessaging::WH_KEYBOARD_LL,
+                        Some(keyboard_hook),
+                        module,
+                        0,
+                    )?;
+                    let mouse = WindowsAndMessaging::SetWindowsHookExW(
+                        WindowsAndMessaging::WH_MOUSE_LL,
+                        Some(mouse_hook),
+                        module,
+                        0,
+                    )
+                    .inspect_err(|_| {
+                        WindowsAndMessaging::UnhookWindowsHookEx(keyboard);
+                    })?;
+                    Ok([keyboard, mouse])
+                })
+            };
+            let hooks = match hooks {
+                Ok(hooks) => {
+                    inner::message_loop::ensure_message_queue();
+                    let _ = started_sender.send(Ok(unsafe { GetCurrentThreadId() }));
+                    hooks
+                }
+                Err(error) => {
+                    let _ = started_sender.send(Err(error));
+                    return;
+                }
+            };
+            inner::message_loop::run_message_loop();
+            for hook in hooks {
+                unsafe { WindowsAndMessaging::UnhookWindowsHookEx(hook) };
+            }
+        });
+        let thread_id = started_receiver
+            .recv()
+            .expect("listener thread exited before installing hooks")?;
+        Ok((
+            Listener {
+                thread_id,
+                thread: Some(thread),
+            },
+            event_receiver,
+        ))
+    }
+
+    /// Remove the hooks and wait for the listener thread to exit.
+    pub fn stop(self) {
+        // `Drop` does the work
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        // the thread never exits without `WM_QUIT`, joining it would hang
+        if inner::message_loop::post_quit(self.thread_id).is_err() {
+            return;
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::{fs, process::ExitCode};
+
+use kemuler_windows::{
+    cli::{self, Action, Cli},
+    dry_run,
+    script::{Script, Statement},
+    server, TrySimulate, Windows,
+};
+
+fn main() -> ExitCode {
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(error) => {
+            eprintln!("error: {error}\n\n{}", cli::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let mut windows = Windows::new();
+    if cli.dry_run {
+        windows = windows.with_dry_run(dry_run::Stderr);
+    }
+    let (path, script) = match cli.action {
+        Action::Help => {
+            print!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Action::ListKeys => {
+            print!("{}", cli::key_list());
+            return ExitCode::SUCCESS;
+        }
+        Action::Commands(commands) => (
+            None,
+            Script {
+                statements: commands
+                    .into_iter()
+                    .map(|command| Statement { line: 1, command })
+                    .collect(),
+            },
+        ),
+        Action::Serve { tcp } => {
+            let result = match tcp {
+                Some(address) => server::serve_tcp(&mut windows, address),
+                None => server::serve_stdin(&mut windows),
+            };
+            if let Err(error) = result {
+                eprintln!("error: {error}");
+                return ExitCode::FAILURE;
+            }
+            return ExitCode::SUCCESS;
+        }
+        Action::Run(path) => {
+            let parsed = fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|source| Script::parse(&source).map_err(|error| error.to_string()));
+            match parsed {
+                Ok(script) => (Some(path), script),
+                Err(error) => {
+                    eprintln!("error: {}: {error}", path.display());
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+    };
+    // input can be blocked, e.g. by UIPI or in a session without a desktop
+    for statement in script.statements {
+        if let Err(error) = windows.try_simulate(statement.command) {
+            match &path {
+                Some(path) => eprintln!(
+                    "error: {}: line {}: {error}",
+                    path.display(),
+                    statement.line
+                ),
+                None => eprintln!("error: {error}"),
+            }
+            windows.release_all();
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,424 @@
+//! Synthetic pen input with `InjectSyntheticPointerInput`.
+//!
+//! [`Windows`](crate::Windows) keeps the state of one synthetic pen
+//! and injects the whole [`PenState`] every time an input changes it.
+//! ```no_run
+//! # use kemuler::{prelude::*, simulator::Simulate};
+//! # use kemuler_windows::{Windows, pen::*};
+//! let mut windows = Windows::new();
+//! windows.simulate(PenPosition.move_to(400, 300));
+//! windows.simulate(PenPressure.set_to(600));
+//! windows.simulate(PenButton::Tip.down());
+//! windows.simulate(PenPosition.move_to(450, 320));
+//! windows.simulate(PenButton::Tip.up());
+//! windows.simulate(PenInRange.leave());
+//! ```
+//!
+//! [`Stroke`] generates the states of a whole stroke along a path.
+//! ```no_run
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{Windows, pen::*};
+//! let stroke = Stroke::new([(100, 100), (300, 150), (500, 100)])
+//!     .with_steps_per_segment(20)
+//!     .with_pressure(PressureCurve::Taper { peak: 900 });
+//! Windows::new().simulate(stroke);
+//! ```
+
+use std::{error::Error, f64::consts::PI, fmt, time::Duration};
+
+use kemuler::input_event::SetTo;
+use windows::Win32::{
+    Foundation::POINT,
+    UI::{Input::Pointer, WindowsAndMessaging},
+};
+
+/// Maximum [`PenState::pressure`]
+pub const MAX_PRESSURE: u32 = 1024;
+
+/// Time between states when simulating a [`Stroke`]
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Buttons of a pen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PenButton {
+    /// The pen tip touching the screen
+    Tip,
+    /// The button on the side of the pen
+    Barrel,
+    /// The eraser end or eraser button
+    Eraser,
+}
+
+impl PenButton {
+    button_like_impl_body! {}
+}
+
+impl fmt::Display for PenButton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// Position of the pen in virtual desktop pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PenPosition;
+
+impl PenPosition {
+    pub fn move_to(self, x: i32, y: i32) -> SetTo<Self, (i32, i32)> {
+        SetTo::new(self, (x, y))
+    }
+}
+
+/// Pressure of the pen tip from 0 to [`MAX_PRESSURE`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PenPressure;
+
+impl PenPressure {
+    pub fn set_to(self, pressure: u32) -> SetTo<Self, u32> {
+        SetTo::new(self, pressure)
+    }
+}
+
+/// Tilt of the pen in degrees from -90 to 90 along the x and y axes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PenTilt;
+
+impl PenTilt {
+    pub fn set_to(self, x: i32, y: i32) -> SetTo<Self, (i32, i32)> {
+        SetTo::new(self, (x, y))
+    }
+}
+
+/// Clockwise rotation of the pen in degrees from 0 to 359
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PenRotation;
+
+impl PenRotation {
+    pub fn set_to(self, rotation: u32) -> SetTo<Self, u32> {
+        SetTo::new(self, rotation)
+    }
+}
+
+/// Whether the pen is close enough to the screen to be detected.
+/// Every other pen input brings the pen in range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PenInRange;
+
+impl PenInRange {
+    pub fn set_to(self, to: bool) -> SetTo<Self, bool> {
+        SetTo::new(self, to)
+    }
+
+    pub fn enter(self) -> SetTo<Self, bool> {
+        self.set_to(true)
+    }
+
+    /// Leave range, lifting the tip and releasing the buttons.
+    pub fn leave(self) -> SetTo<Self, bool> {
+        self.set_to(false)
+    }
+}
+
+/// Error returned by [`PenState::validate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PenError {
+    /// The pressure is more than [`MAX_PRESSURE`]
+    PressureOutOfRange(u32),
+    /// The rotation is not between 0 and 359
+    RotationOutOfRange(u32),
+    /// The tilt is not between -90 and 90
+    TiltOutOfRange(i32),
+}
+
+impl fmt::Display for PenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PenError::PressureOutOfRange(pressure) => {
+                write!(f, "pen pressure {pressure} is more than {MAX_PRESSURE}")
+            }
+            PenError::RotationOutOfRange(rotation) => {
+                write!(f, "pen rotation {rotation} is not between 0 and 359")
+            }
+            PenError::TiltOutOfRange(tilt) => {
+                write!(f, "pen tilt {tilt} is not between -90 and 90")
+            }
+        }
+    }
+}
+
+impl Error for PenError {}
+
+/// Everything injected about the pen at one point in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PenState {
+    /// Position in virtual desktop pixels
+    pub position: (i32, i32),
+    pub is_in_range: bool,
+    /// The tip touches the screen
+    pub is_in_contact: bool,
+    pub is_barrel_pressed: bool,
+    pub is_eraser: bool,
+    /// Pressure from 0 to [`MAX_PRESSURE`]
+    pub pressure: u32,
+    /// Degrees from -90 to 90 along the x and y axes
+    pub tilt: (i32, i32),
+    /// Degrees from 0 to 359
+    pub rotation: u32,
+}
+
+impl PenState {
+    /// Change the state of a button, this brings the pen in range.
+    pub fn with_button(mut self, button: PenButton, is_down: bool) -> PenState {
+        match button {
+            PenButton::Tip => self.is_in_contact = is_down,
+            PenButton::Barrel => self.is_barrel_pressed = is_down,
+            PenButton::Eraser => self.is_eraser = is_down,
+        }
+        self.is_in_range = true;
+        self
+    }
+
+    /// Enter or leave range. Leaving range lifts the tip and releases the buttons.
+    pub fn with_in_range(mut self, is_in_range: bool) -> PenState {
+        if !is_in_range {
+            self.is_in_contact = false;
+            self.is_barrel_pressed = false;
+            self.is_eraser = false;
+        }
+        self.is_in_range = is_in_range;
+        self
+    }
+
+    /// Check that `InjectSyntheticPointerInput` can accept the state.
+    /// ```
+    /// # use kemuler_windows::pen::*;
+    /// assert_eq!(PenState::default().validate(), Ok(()));
+    /// let state = PenState { tilt: (0, 120), ..Default::default() };
+    /// assert_eq!(state.validate(), Err(PenError::TiltOutOfRange(120)));
+    /// ```
+    pub fn validate(&self) -> Result<(), PenError> {
+        if self.pressure > MAX_PRESSURE {
+            return Err(PenError::PressureOutOfRange(self.pressure));
+        }
+        if self.rotation > 359 {
+            return Err(PenError::RotationOutOfRange(self.rotation));
+        }
+        if let Some(tilt) = [self.tilt.0, self.tilt.1]
+            .into_iter()
+            .find(|tilt| !(-90..=90).contains(tilt))
+        {
+            return Err(PenError::TiltOutOfRange(tilt));
+        }
+        Ok(())
+    }
+
+    /// Convert into the structure passed to `InjectSyntheticPointerInput`.
+    /// `previous` is the last injected state, it decides whether the tip went down or up.
+    /// ```
+    /// # use kemuler_windows::pen::*;
+    /// # use windows::Win32::UI::Input::Pointer::*;
+    /// let hovering = PenState { position: (10, 20), is_in_range: true, ..Default::default() };
+    /// let touching = PenState { pressure: 300, ..hovering.with_button(PenButton::Tip, true) };
+    ///
+    /// let info = touching.into_windows(&hovering);
+    /// assert_eq!(
+    ///     info.pointerInfo.pointerFlags,
+    ///     POINTER_FLAG_DOWN | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT | POINTER_FLAG_FIRSTBUTTON
+    /// );
+    /// assert_eq!(info.pointerInfo.ButtonChangeType, POINTER_CHANGE_FIRSTBUTTON_DOWN);
+    /// assert_eq!(info.pressure, 300);
+    ///
+    /// let info = hovering.into_windows(&touching);
+    /// assert_eq!(info.pointerInfo.pointerFlags, POINTER_FLAG_UP | POINTER_FLAG_INRANGE);
+    /// assert_eq!(info.pointerInfo.ButtonChangeType, POINTER_CHANGE_FIRSTBUTTON_UP);
+    ///
+    /// let info = hovering.into_windows(&hovering);
+    /// assert_eq!(info.pointerInfo.pointerFlags, POINTER_FLAG_UPDATE | POINTER_FLAG_INRANGE);
+    /// ```
+    pub fn into_windows(self, previous: &PenState) -> Pointer::POINTER_PEN_INFO {
+        let mut pointer_flags = match (previous.is_in_contact, self.is_in_contact) {
+            (false, true) => Pointer::POINTER_FLAG_DOWN,
+            (true, false) => Pointer::POINTER_FLAG_UP,
+            _ => Pointer::POINTER_FLAG_UPDATE,
+        };
+        if self.is_in_range {
+            pointer_flags |= Pointer::POINTER_FLAG_INRANGE;
+        }
+        if self.is_in_contact {
+            pointer_flags |= Pointer::POINTER_FLAG_INCONTACT | Pointer::POINTER_FLAG_FIRSTBUTTON;
+        }
+        if self.is_barrel_pressed {
+            pointer_flags |= Pointer::POINTER_FLAG_SECONDBUTTON;
+        }
+        let button_change_type = match (
+            (previous.is_in_contact, self.is_in_contact),
+            (previous.is_barrel_pressed, self.is_barrel_pressed),
+        ) {
+            ((false, true), _) => Pointer::POINTER_CHANGE_FIRSTBUTTON_DOWN,
+            ((true, false), _) => Pointer::POINTER_CHANGE_FIRSTBUTTON_UP,
+            (_, (false, true)) => Pointer::POINTER_CHANGE_SECONDBUTTON_DOWN,
+            (_, (true, false)) => Pointer::POINTER_CHANGE_SECONDBUTTON_UP,
+            _ => Pointer::POINTER_CHANGE_NONE,
+        };
+        let mut pen_flags = WindowsAndMessaging::PEN_FLAG_NONE;
+        if self.is_barrel_pressed {
+            pen_flags |= WindowsAndMessaging::PEN_FLAG_BARREL;
+        }
+        if self.is_eraser {
+            pen_flags |= WindowsAndMessaging::PEN_FLAG_ERASER;
+        }
+        let (x, y) = self.position;
+        Pointer::POINTER_PEN_INFO {
+            pointerInfo: Pointer::POINTER_INFO {
+                pointerType: WindowsAndMessaging::PT_PEN,
+                pointerFlags: pointer_flags,
+                ptPixelLocation: POINT { x, y },
+                ButtonChangeType: button_change_type,
+                ..Default::default()
+            },
+            penFlags: pen_flags,
+            penMask: WindowsAndMessaging::PEN_MASK_PRESSURE
+                | WindowsAndMessaging::PEN_MASK_ROTATION
+                | WindowsAndMessaging::PEN_MASK_TILT_X
+                | WindowsAndMessaging::PEN_MASK_TILT_Y,
+            pressure: self.pressure,
+            rotation: self.rotation,
+            tiltX: self.tilt.0,
+            tiltY: self.tilt.1,
+        }
+    }
+}
+
+/// How pressure changes along a [`Stroke`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PressureCurve {
+    Constant(u32),
+    /// Change linearly from the start to the end of the stroke
+    Linear {
+        from: u32,
+        to: u32,
+    },
+    /// Rise from 0 to `peak` in the middle of the stroke and fall back to 0, like a brush stroke
+    Taper {
+        peak: u32,
+    },
+}
+
+impl Default for PressureCurve {
+    fn default() -> Self {
+        PressureCurve::Constant(MAX_PRESSURE / 2)
+    }
+}
+
+impl PressureCurve {
+    /// Pressure at `progress` from 0.0 at the start to 1.0 at the end of the stroke
+    /// ```
+    /// # use kemuler_windows::pen::*;
+    /// assert_eq!(PressureCurve::Linear { from: 200, to: 1000 }.pressure_at(0.25), 400);
+    /// assert_eq!(PressureCurve::Taper { peak: 800 }.pressure_at(0.5), 800);
+    /// assert_eq!(PressureCurve::Taper { peak: 800 }.pressure_at(1.0), 0);
+    /// ```
+    pub fn pressure_at(&self, progress: f64) -> u32 {
+        let progress = progress.clamp(0.0, 1.0);
+        match *self {
+            PressureCurve::Constant(pressure) => pressure,
+            PressureCurve::Linear { from, to } => {
+                (from as f64 + (to as f64 - from as f64) * progress).round() as u32
+            }
+            PressureCurve::Taper { peak } => (peak as f64 * (PI * progress).sin()).round() as u32,
+        }
+    }
+}
+
+/// A pen stroke along a path of points in virtual desktop pixels
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Stroke {
+    pub path: Vec<(i32, i32)>,
+    pub pressure: PressureCurve,
+    /// Number of states between two consecutive points of the path
+    pub steps_per_segment: u32,
+}
+
+impl Stroke {
+    pub fn new(path: impl IntoIterator<Item = (i32, i32)>) -> Stroke {
+        Stroke {
+            path: path.into_iter().collect(),
+            pressure: PressureCurve::default(),
+            steps_per_segment: 1,
+        }
+    }
+
+    pub fn with_pressure(mut self, pressure: PressureCurve) -> Stroke {
+        self.pressure = pressure;
+        self
+    }
+
+    pub fn with_steps_per_segment(mut self, steps_per_segment: u32) -> Stroke {
+        self.steps_per_segment = steps_per_segment.max(1);
+        self
+    }
+
+    /// Points the pen tip passes through, including interpolated points
+    /// ```
+    /// # use kemuler_windows::pen::Stroke;
+    /// let stroke = Stroke::new([(i32::MIN, 0), (i32::MAX, 10)]).with_steps_per_segment(2);
+    /// assert_eq!(stroke.points(), [(i32::MIN, 0), (-1, 5), (i32::MAX, 10)]);
+    /// ```
+    pub fn points(&self) -> Vec<(i32, i32)> {
+        let mut points = self.path.first().copied().into_iter().collect::<Vec<_>>();
+        for segment in self.path.windows(2) {
+            let ((from_x, from_y), (to_x, to_y)) = (segment[0], segment[1]);
+            let (from_x, from_y, to_x, to_y) =
+                (from_x as i64, from_y as i64, to_x as i64, to_y as i64);
+            let steps = self.steps_per_segment.max(1) as i64;
+            points.extend((1..=steps).map(|step| {
+                (
+                    (from_x + (to_x - from_x) * step / steps) as i32,
+                    (from_y + (to_y - from_y) * step / steps) as i32,
+                )
+            }));
+        }
+        points
+    }
+
+    /// States of the pen during the stroke:
+    /// hovering over the first point, touching every point, then lifting at the last point.
+    /// ```
+    /// # use kemuler_windows::pen::*;
+    /// let stroke = Stroke::new([(0, 0), (10, 0)])
+    ///     .with_steps_per_segment(2)
+    ///     .with_pressure(PressureCurve::Linear { from: 0, to: 1000 });
+    /// let states = stroke
+    ///     .states()
+    ///     .iter()
+    ///     .map(|state| (state.position, state.is_in_contact, state.pressure))
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(states, [
+    ///     ((0, 0), false, 0),
+    ///     ((0, 0), true, 0),
+    ///     ((5, 0), true, 500),
+    ///     ((10, 0), true, 1000),
+    ///     ((10, 0), false, 0),
+    /// ]);
+    /// ```
+    pub fn states(&self) -> Vec<PenState> {
+        let points = self.points();
+        let (Some(&first), Some(&last)) = (points.first(), points.last()) else {
+            return Vec::new();
+        };
+        let hovering = |position| PenState {
+            position,
+            is_in_range: true,
+            ..Default::default()
+        };
+        let last_index = (points.len() - 1).max(1) as f64;
+        let mut states = vec![hovering(first)];
+        states.extend(points.iter().enumerate().map(|(i, &position)| PenState {
+            is_in_contact: true,
+            pressure: self.pressure.pressure_at(i as f64 / last_index),
+            ..hovering(position)
+        }));
+        states.push(hovering(last));
+        states
+    }
+}
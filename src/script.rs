@@ -0,0 +1,716 @@
+//! A small text language for automation scripts.
+//!
+//! Every line is one command, `#` starts a comment.
+//!
+//! | Command | Meaning |
+//! | --- | --- |
+//! | `key ctrl+s tab` | Press and release each chord, modifiers are released in reverse order |
+//! | `down shift`, `up shift` | Press or release a key |
+//! | `type "hello\n"` | Type text, `\"`, `\\`, `\n` and `\t` are escaped |
+//! | `move 100 200` | Move the mouse to a position in virtual desktop pixels |
+//! | `click`, `click right 2` | Click a mouse button, left by default, once by default, at most 1000 times |
+//! | `mousedown left`, `mouseup left` | Press or release a mouse button |
+//! | `scroll -3`, `scroll 0 2` | Scroll vertically and horizontally in wheel notches, negative is down and left |
+//! | `wait 250ms`, `wait 1.5s` | Pause |
+//!
+//! Scripts are parsed into a [`Script`] and can be run on any simulator.
+//! ```no_run
+//! # use kemuler_windows::{Windows, script::Script};
+//! let script = Script::parse(r#"
+//!     key win+r
+//!     wait 500ms
+//!     type "notepad\n"
+//! "#).unwrap();
+//! script.run(&mut Windows::new());
+//! ```
+//!
+//! Running on a [`Macro`](crate::input_macro::Macro) records the events instead.
+//! ```
+//! # use std::time::Duration;
+//! # use kemuler_windows::{VirtualKey, input_macro::*, script::Script};
+//! let script = Script::parse("key ctrl+s\nwait 10ms\nclick").unwrap();
+//! let mut recorded = Macro::new();
+//! let mut waited = Duration::ZERO;
+//! script.run_with_sleep(&mut recorded, |duration| waited += duration);
+//! assert_eq!(waited, Duration::from_millis(10));
+//! assert_eq!(recorded.events.len(), 6);
+//! ```
+
+use std::{error::Error, fmt, ops::RangeInclusive, str::FromStr, thread, time::Duration};
+
+use kemuler::{
+    common_inputs,
+    input_event::{ChangeBy, SetTo},
+    simulator::Simulate,
+};
+use windows::Win32::UI::WindowsAndMessaging::WHEEL_DELTA;
+
+use crate::{
+    input_macro::{Macro, MacroEvent, TimedEvent},
+    MouseButton, VirtualKey,
+};
+
+/// A command of a [`Script`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Command {
+    /// Press and release each chord in turn
+    Key(Vec<Vec<VirtualKey>>),
+    KeyDown(VirtualKey),
+    KeyUp(VirtualKey),
+    Type(String),
+    Move(i32, i32),
+    Click {
+        button: MouseButton,
+        count: u32,
+    },
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    /// Scroll by vertical and horizontal wheel notches
+    Scroll {
+        vertical: i32,
+        horizontal: i32,
+    },
+    Wait(Duration),
+}
+
+impl Command {
+    /// Most clicks of one [`Command::Click`]
+    pub const MAX_CLICK_COUNT: u32 = 1000;
+    /// Most wheel notches of one [`Command::Scroll`] in each direction,
+    /// more would overflow a wheel delta
+    pub const MAX_SCROLL_NOTCHES: i32 = i32::MAX / WHEEL_DELTA as i32;
+
+    /// Click `count` times.
+    /// Return `None` if `count` is above [`Command::MAX_CLICK_COUNT`].
+    /// ```
+    /// # use kemuler_windows::{MouseButton, script::Command};
+    /// assert_eq!(Command::click(MouseButton::Left, 2), Some(Command::Click { button: MouseButton::Left, count: 2 }));
+    /// assert_eq!(Command::click(MouseButton::Left, u32::MAX), None);
+    /// ```
+    pub fn click(button: MouseButton, count: u32) -> Option<Command> {
+        (count <= Command::MAX_CLICK_COUNT).then_some(Command::Click { button, count })
+    }
+
+    /// Scroll by wheel notches.
+    /// Return `None` if an amount is beyond [`Command::MAX_SCROLL_NOTCHES`].
+    /// ```
+    /// # use kemuler_windows::script::Command;
+    /// assert_eq!(Command::scroll(-3, 0), Some(Command::Scroll { vertical: -3, horizontal: 0 }));
+    /// assert_eq!(Command::scroll(0, 99_999_999), None);
+    /// ```
+    pub fn scroll(vertical: i32, horizontal: i32) -> Option<Command> {
+        let range = -Command::MAX_SCROLL_NOTCHES..=Command::MAX_SCROLL_NOTCHES;
+        (range.contains(&vertical) && range.contains(&horizontal)).then_some(Command::Scroll {
+            vertical,
+            horizontal,
+        })
+    }
+
+    /// Events simulated by the command, empty for [`Command::Wait`].
+    /// Counts and amounts beyond the limits checked by [`Command::click`]
+    /// and [`Command::scroll`] are clamped to them.
+    /// ```
+    /// # use kemuler_windows::{VirtualKey, input_macro::MacroEvent, script::Command};
+    /// let command = Command::Key(vec![vec![VirtualKey::Control, VirtualKey::S]]);
+    /// assert_eq!(command.events(), [
+    ///     MacroEvent::Key { key: VirtualKey::Control, is_down: true },
+    ///     MacroEvent::Key { key: VirtualKey::S, is_down: true },
+    ///     MacroEvent::Key { key: VirtualKey::S, is_down: false },
+    ///     MacroEvent::Key { key: VirtualKey::Control, is_down: false },
+    /// ]);
+    /// assert_eq!(
+    ///     Command::Scroll { vertical: -3, horizontal: 0 }.events(),
+    ///     [MacroEvent::MouseScroll { delta: (0, -360) }]
+    /// );
+    /// ```
+    pub fn events(&self) -> Vec<MacroEvent> {
+        let key = |key, is_down| MacroEvent::Key { key, is_down };
+        let button = |button, is_down| MacroEvent::MouseButton { button, is_down };
+        match self {
+            Command::Key(chords) => chords
+                .iter()
+                .flat_map(|chord| {
+                    let downs = chord.iter().map(move |&k| key(k, true));
+                    let ups = chord.iter().rev().map(move |&k| key(k, false));
+                    downs.chain(ups)
+                })
+                .collect(),
+            Command::KeyDown(k) => vec![key(*k, true)],
+            Command::KeyUp(k) => vec![key(*k, false)],
+            Command::Type(text) => text
+                .chars()
+                .flat_map(|char| {
+                    [
+                        MacroEvent::Char {
+                            char,
+                            is_down: true,
+                        },
+                        MacroEvent::Char {
+                            char,
+                            is_down: false,
+                        },
+                    ]
+                })
+                .collect(),
+            Command::Move(x, y) => vec![MacroEvent::MouseMove { position: (*x, *y) }],
+            Command::Click { button: b, count } => (0..*count.min(&Command::MAX_CLICK_COUNT))
+                .flat_map(|_| [button(*b, true), button(*b, false)])
+                .collect(),
+            Command::MouseDown(b) => vec![button(*b, true)],
+            Command::MouseUp(b) => vec![button(*b, false)],
+            Command::Scroll {
+                vertical,
+                horizontal,
+            } => {
+                let delta = |notches: i32| {
+                    notches.clamp(-Command::MAX_SCROLL_NOTCHES, Command::MAX_SCROLL_NOTCHES)
+                        * WHEEL_DELTA as i32
+                };
+                vec![MacroEvent::MouseScroll {
+                    delta: (delta(*horizontal), delta(*vertical)),
+                }]
+            }
+            Command::Wait(_) => Vec::new(),
+        }
+    }
+}
+
+/// Commands that move the mouse from `from` to `to` in `steps` even steps spread over `duration`.
+/// ```
+/// # use std::time::Duration;
+/// # use kemuler_windows::script::{smooth_move, Command};
+/// assert_eq!(smooth_move((0, 0), (100, 50), Duration::from_millis(20), 2), [
+///     Command::Move(50, 25),
+///     Command::Wait(Duration::from_millis(10)),
+///     Command::Move(100, 50),
+/// ]);
+/// ```
+pub fn smooth_move(
+    from: (i32, i32),
+    to: (i32, i32),
+    duration: Duration,
+    steps: u32,
+) -> Vec<Command> {
+    let steps = steps.max(1);
+    let interval = duration / steps;
+    let mut commands = Vec::new();
+    for step in 1..=steps {
+        if step != 1 && !interval.is_zero() {
+            commands.push(Command::Wait(interval));
+        }
+        let along =
+            |from: i32, to: i32| from + ((to - from) as i64 * step as i64 / steps as i64) as i32;
+        commands.push(Command::Move(along(from.0, to.0), along(from.1, to.1)));
+    }
+    commands
+}
+
+/// A [`Command`] with the line it is on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Statement {
+    /// Line number starting at 1
+    pub line: usize,
+    pub command: Command,
+}
+
+/// What is wrong in a script
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ParseErrorKind {
+    UnknownCommand(String),
+    /// A required argument is missing, the description says what was expected
+    MissingArgument(&'static str),
+    UnexpectedArgument(String),
+    UnknownKey(String),
+    UnknownButton(String),
+    InvalidNumber(String),
+    InvalidDuration(String),
+    ExpectedString,
+    UnterminatedString,
+    InvalidEscape(char),
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnknownCommand(command) => write!(f, "unknown command `{command}`"),
+            ParseErrorKind::MissingArgument(expected) => write!(f, "missing {expected}"),
+            ParseErrorKind::UnexpectedArgument(argument) => {
+                write!(f, "unexpected argument `{argument}`")
+            }
+            ParseErrorKind::UnknownKey(key) => write!(f, "unknown key `{key}`"),
+            ParseErrorKind::UnknownButton(button) => write!(f, "unknown mouse button `{button}`"),
+            ParseErrorKind::InvalidNumber(number) => write!(f, "invalid number `{number}`"),
+            ParseErrorKind::InvalidDuration(duration) => write!(
+                f,
+                "invalid duration `{duration}`, expected a number followed by `ms` or `s`"
+            ),
+            ParseErrorKind::ExpectedString => write!(f, "expected a quoted string"),
+            ParseErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            ParseErrorKind::InvalidEscape(char) => write!(f, "invalid escape `\\{char}`"),
+        }
+    }
+}
+
+/// Error returned by [`Script::parse`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseError {
+    /// Line number starting at 1
+    pub line: usize,
+    /// Column in characters starting at 1
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.kind
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// A parsed script
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Script {
+    pub statements: Vec<Statement>,
+}
+
+impl Script {
+    /// Parse a script.
+    /// ```
+    /// # use std::time::Duration;
+    /// # use kemuler_windows::{MouseButton, script::*};
+    /// let script = Script::parse("move 100 200 # top left\n\nclick right 2\nwait 1.5s").unwrap();
+    /// let commands = script.statements.iter().map(|s| (s.line, s.command.clone())).collect::<Vec<_>>();
+    /// assert_eq!(commands, [
+    ///     (1, Command::Move(100, 200)),
+    ///     (3, Command::Click { button: MouseButton::Right, count: 2 }),
+    ///     (4, Command::Wait(Duration::from_millis(1500))),
+    /// ]);
+    ///
+    /// let error = Script::parse("key ctrl+s\nkey ctrl+sss").unwrap_err();
+    /// assert_eq!(error.to_string(), "line 2, column 10: unknown key `sss`");
+    ///
+    /// let error = Script::parse("scroll 0 99999999").unwrap_err();
+    /// assert_eq!(error.to_string(), "line 1, column 10: invalid number `99999999`");
+    ///
+    /// let error = Script::parse("click left 4294967295").unwrap_err();
+    /// assert_eq!(error.to_string(), "line 1, column 12: invalid number `4294967295`");
+    ///
+    /// let error = Script::parse("type \"unterminated").unwrap_err();
+    /// assert_eq!((error.line, error.column, error.kind), (1, 6, ParseErrorKind::UnterminatedString));
+    /// ```
+    pub fn parse(source: &str) -> Result<Script, ParseError> {
+        let statements = source
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| parse_line(index + 1, line).transpose())
+            .collect::<Result<_, _>>()?;
+        Ok(Script { statements })
+    }
+
+    /// Every event with the time it happens at, waits are added up
+    pub fn to_macro(&self) -> Macro {
+        let mut time = Duration::ZERO;
+        let mut events = Vec::new();
+        for statement in &self.statements {
+            match statement.command {
+                Command::Wait(duration) => time += duration,
+                ref command => events.extend(
+                    command
+                        .events()
+                        .into_iter()
+                        .map(|event| TimedEvent { time, event }),
+                ),
+            }
+        }
+        Macro { events }
+    }
+
+    /// Run the script, blocking the current thread during waits.
+    pub fn run<S>(&self, simulator: &mut S)
+    where
+        S: Simulate<SetTo<VirtualKey, bool>>
+            + Simulate<SetTo<common_inputs::Char, bool>>
+            + Simulate<SetTo<MouseButton, bool>>
+            + Simulate<SetTo<common_inputs::MousePosition, (i32, i32)>>
+            + Simulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>>,
+    {
+        self.run_with_sleep(simulator, thread::sleep)
+    }
+
+    /// Run the script, calling `sleep` for waits.
+    pub fn run_with_sleep<S>(&self, simulator: &mut S, mut sleep: impl FnMut(Duration))
+    where
+        S: Simulate<SetTo<VirtualKey, bool>>
+            + Simulate<SetTo<common_inputs::Char, bool>>
+            + Simulate<SetTo<MouseButton, bool>>
+            + Simulate<SetTo<common_inputs::MousePosition, (i32, i32)>>
+            + Simulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>>,
+    {
+        for statement in &self.statements {
+            match statement.command {
+                Command::Wait(duration) => sleep(duration),
+                ref command => {
+                    for event in command.events() {
+                        event.simulate_with(simulator);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Script {
+    type Err = ParseError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Script::parse(source)
+    }
+}
+
+/// Find a key by name, ignoring case.
+/// Common names like `ctrl`, `win`, `esc` and `up` are accepted
+/// as well as [`VirtualKey`] variant names and `VK_` constant names.
+/// ```
+/// # use kemuler_windows::{VirtualKey, script::key_from_name};
+/// assert_eq!(key_from_name("Ctrl"), Some(VirtualKey::Control));
+/// assert_eq!(key_from_name("7"), Some(VirtualKey::Num7));
+/// assert_eq!(key_from_name("pagedown"), Some(VirtualKey::PageDown));
+/// assert_eq!(key_from_name("VK_OEM_PLUS"), Some(VirtualKey::OemPlus));
+/// assert_eq!(key_from_name("nope"), None);
+/// ```
+pub fn key_from_name(name: &str) -> Option<VirtualKey> {
+    let lowercase = name.to_ascii_lowercase();
+    let key = match lowercase.as_str() {
+        "ctrl" | "control" => VirtualKey::Control,
+        "alt" => VirtualKey::Alt,
+        "shift" => VirtualKey::Shift,
+        "win" | "super" | "meta" | "cmd" => VirtualKey::LWin,
+        "enter" | "return" => VirtualKey::Enter,
+        "esc" | "escape" => VirtualKey::Escape,
+        "bs" | "backspace" => VirtualKey::Backspace,
+        "del" | "delete" => VirtualKey::Delete,
+        "ins" | "insert" => VirtualKey::Insert,
+        "pgup" | "pageup" => VirtualKey::PageUp,
+        "pgdn" | "pagedown" => VirtualKey::PageDown,
+        "up" => VirtualKey::UpArrow,
+        "down" => VirtualKey::DownArrow,
+        "left" => VirtualKey::LeftArrow,
+        "right" => VirtualKey::RightArrow,
+        "menu" | "apps" => VirtualKey::Apps,
+        "prtsc" | "printscreen" => VirtualKey::PrintScreen,
+        _ => {
+            let mut chars = lowercase.chars();
+            if let (Some(char), None) = (chars.next(), chars.next()) {
+                if let Some(key) = VirtualKey::from_alphanumeric(char) {
+                    return Some(key);
+                }
+            }
+            return VirtualKey::ALL.iter().copied().find(|key| {
+                format!("{:?}", key).eq_ignore_ascii_case(name)
+                    || key.win32_name().eq_ignore_ascii_case(name)
+            });
+        }
+    };
+    Some(key)
+}
+
+/// Find a mouse button by name, ignoring case.
+/// ```
+/// # use kemuler_windows::{MouseButton, script::button_from_name};
+/// assert_eq!(button_from_name("Right"), Some(MouseButton::Right));
+/// assert_eq!(button_from_name("back"), Some(MouseButton::X2));
+/// ```
+pub fn button_from_name(name: &str) -> Option<MouseButton> {
+    match name.to_ascii_lowercase().as_str() {
+        "left" => Some(MouseButton::Left),
+        "middle" => Some(MouseButton::Middle),
+        "right" => Some(MouseButton::Right),
+        "x1" | "forward" => Some(MouseButton::X1),
+        "x2" | "back" | "backward" => Some(MouseButton::X2),
+        _ => None,
+    }
+}
+
+/// A word or quoted string on a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    text: String,
+    /// Column in characters starting at 1
+    column: usize,
+    is_string: bool,
+}
+
+/// Split a line into tokens, stopping at a comment
+fn tokenize(line_number: usize, line: &str) -> Result<Vec<Token>, ParseError> {
+    let error = |column, kind| ParseError {
+        line: line_number,
+        column,
+        kind,
+    };
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().enumerate().peekable();
+    while let Some(&(index, char)) = chars.peek() {
+        let column = index + 1;
+        if char.is_whitespace() {
+            chars.next();
+        } else if char == '#' {
+            break;
+        } else if char == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err(error(column, ParseErrorKind::UnterminatedString)),
+                    Some((_, '"')) => break,
+                    Some((escape_index, '\\')) => match chars.next() {
+                        Some((_, '"')) => text.push('"'),
+                        Some((_, '\\')) => text.push('\\'),
+                        Some((_, 'n')) => text.push('\n'),
+                        Some((_, 't')) => text.push('\t'),
+                        Some((_, other)) => {
+                            return Err(error(
+                                escape_index + 1,
+                                ParseErrorKind::InvalidEscape(other),
+                            ))
+                        }
+                        None => return Err(error(column, ParseErrorKind::UnterminatedString)),
+                    },
+                    Some((_, char)) => text.push(char),
+                }
+            }
+            tokens.push(Token {
+                text,
+                column,
+                is_string: true,
+            });
+        } else {
+            let mut text = String::new();
+            while let Some(&(_, char)) = chars.peek() {
+                if char.is_whitespace() || char == '#' || char == '"' {
+                    break;
+                }
+                text.push(char);
+                chars.next();
+            }
+            tokens.push(Token {
+                text,
+                column,
+                is_string: false,
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse one line, return `None` for blank and comment lines
+fn parse_line(line_number: usize, line: &str) -> Result<Option<Statement>, ParseError> {
+    let tokens = tokenize(line_number, line)?;
+    let Some((name, arguments)) = tokens.split_first() else {
+        return Ok(None);
+    };
+    let end_column = line.chars().count() + 1;
+    let mut arguments = Arguments {
+        line: line_number,
+        end_column,
+        tokens: arguments.iter(),
+    };
+    let command = match name.text.to_ascii_lowercase().as_str() {
+        "key" => {
+            let mut chords = vec![arguments.chord()?];
+            while arguments.has_more() {
+                chords.push(arguments.chord()?);
+            }
+            Command::Key(chords)
+        }
+        "down" => Command::KeyDown(arguments.key()?),
+        "up" => Command::KeyUp(arguments.key()?),
+        "type" => Command::Type(arguments.string()?),
+        "move" => Command::Move(
+            arguments.number("x position")?,
+            arguments.number("y position")?,
+        ),
+        "click" => {
+            let button = arguments.optional_button()?.unwrap_or(MouseButton::Left);
+            let count = if arguments.has_more() {
+                arguments.number_in("click count", 0..=Command::MAX_CLICK_COUNT)?
+            } else {
+                1
+            };
+            Command::Click { button, count }
+        }
+        "mousedown" => Command::MouseDown(arguments.button()?),
+        "mouseup" => Command::MouseUp(arguments.button()?),
+        "scroll" => {
+            let notches = -Command::MAX_SCROLL_NOTCHES..=Command::MAX_SCROLL_NOTCHES;
+            Command::Scroll {
+                vertical: arguments.number_in("scroll amount", notches.clone())?,
+                horizontal: if arguments.has_more() {
+                    arguments.number_in("horizontal scroll amount", notches)?
+                } else {
+                    0
+                },
+            }
+        }
+        "wait" => Command::Wait(arguments.duration()?),
+        _ => {
+            return Err(ParseError {
+                line: line_number,
+                column: name.column,
+                kind: ParseErrorKind::UnknownCommand(name.text.clone()),
+            })
+        }
+    };
+    arguments.finish()?;
+    Ok(Some(Statement {
+        line: line_number,
+        command,
+    }))
+}
+
+/// Arguments of a command being parsed
+struct Arguments<'a> {
+    line: usize,
+    end_column: usize,
+    tokens: std::slice::Iter<'a, Token>,
+}
+
+impl<'a> Arguments<'a> {
+    fn error(&self, column: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            line: self.line,
+            column,
+            kind,
+        }
+    }
+
+    fn has_more(&self) -> bool {
+        self.tokens.len() != 0
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<&'a Token, ParseError> {
+        let error = self.error(self.end_column, ParseErrorKind::MissingArgument(expected));
+        self.tokens.next().ok_or(error)
+    }
+
+    fn word(&mut self, expected: &'static str) -> Result<&'a Token, ParseError> {
+        let token = self.next(expected)?;
+        if token.is_string {
+            let column = token.column;
+            return Err(self.error(
+                column,
+                ParseErrorKind::UnexpectedArgument(format!("\"{}\"", token.text)),
+            ));
+        }
+        Ok(token)
+    }
+
+    fn key(&mut self) -> Result<VirtualKey, ParseError> {
+        let token = self.word("key")?;
+        key_from_name(&token.text)
+            .ok_or_else(|| self.error(token.column, ParseErrorKind::UnknownKey(token.text.clone())))
+    }
+
+    fn chord(&mut self) -> Result<Vec<VirtualKey>, ParseError> {
+        let token = self.word("key")?;
+        let mut column = token.column;
+        let mut chord = Vec::new();
+        for name in token.text.split('+') {
+            let key = key_from_name(name)
+                .ok_or_else(|| self.error(column, ParseErrorKind::UnknownKey(name.to_string())))?;
+            chord.push(key);
+            column += name.chars().count() + 1;
+        }
+        Ok(chord)
+    }
+
+    fn button(&mut self) -> Result<MouseButton, ParseError> {
+        let token = self.word("mouse button")?;
+        button_from_name(&token.text).ok_or_else(|| {
+            self.error(
+                token.column,
+                ParseErrorKind::UnknownButton(token.text.clone()),
+            )
+        })
+    }
+
+    /// A button if the next argument is not a number
+    fn optional_button(&mut self) -> Result<Option<MouseButton>, ParseError> {
+        match self.tokens.clone().next() {
+            Some(token) if token.text.parse::<i64>().is_err() => self.button().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn number<T: FromStr>(&mut self, expected: &'static str) -> Result<T, ParseError> {
+        let token = self.word(expected)?;
+        token.text.parse().map_err(|_| {
+            self.error(
+                token.column,
+                ParseErrorKind::InvalidNumber(token.text.clone()),
+            )
+        })
+    }
+
+    /// A number that has to be in `range`
+    fn number_in<T: FromStr + PartialOrd>(
+        &mut self,
+        expected: &'static str,
+        range: RangeInclusive<T>,
+    ) -> Result<T, ParseError> {
+        let token = self.word(expected)?;
+        token
+            .text
+            .parse::<T>()
+            .ok()
+            .filter(|number| range.contains(number))
+            .ok_or_else(|| {
+                self.error(
+                    token.column,
+                    ParseErrorKind::InvalidNumber(token.text.clone()),
+                )
+            })
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        let token = self.next("text")?;
+        if !token.is_string {
+            return Err(self.error(token.column, ParseErrorKind::ExpectedString));
+        }
+        Ok(token.text.clone())
+    }
+
+    fn duration(&mut self) -> Result<Duration, ParseError> {
+        let token = self.word("duration")?;
+        let invalid = || {
+            self.error(
+                token.column,
+                ParseErrorKind::InvalidDuration(token.text.clone()),
+            )
+        };
+        let (number, unit) = if let Some(number) = token.text.strip_suffix("ms") {
+            (number, 0.001)
+        } else if let Some(number) = token.text.strip_suffix('s') {
+            (number, 1.0)
+        } else {
+            return Err(invalid());
+        };
+        let seconds = number.parse::<f64>().map_err(|_| invalid())? * unit;
+        Duration::try_from_secs_f64(seconds).map_err(|_| invalid())
+    }
+
+    fn finish(mut self) -> Result<(), ParseError> {
+        match self.tokens.next() {
+            Some(token) => Err(self.error(
+                token.column,
+                ParseErrorKind::UnexpectedArgument(token.text.clone()),
+            )),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Execute newline-delimited JSON commands.
+//!
+//! Requires the `server` feature.
+//!
+//! Every line is one request, every request gets one reply line in the same order.
+//! `id` is optional and copied to the reply.
+//! ```text
+//! {"id": 1, "type": "key_down", "key": "LShift"}
+//! {"id": 2, "type": "key_up", "key": "LShift"}
+//! {"id": 3, "type": "key", "keys": ["ctrl", "s"]}
+//! {"id": 4, "type": "text", "text": "hello"}
+//! {"id": 5, "type": "mouse_move", "x": 100, "y": 200}
+//! {"id": 6, "type": "mouse_button", "button": "left", "is_down": true}
+//! {"id": 7, "type": "mouse_scroll", "vertical": -3, "horizontal": 0}
+//! {"id": 8, "type": "delay", "ms": 250}
+//! ```
+//! Keys are looked up with [`script::key_from_name`] and buttons with [`script::button_from_name`].
+//! Scrolling is in wheel notches, bounded like [`Command::scroll`].
+//! Delays are at most [`MAX_DELAY`] and requests at most [`MAX_REQUEST_LENGTH`] bytes.
+//! ```text
+//! {"id":1,"ok":true}
+//! {"id":9,"ok":false,"error":"unknown key `Shfit`"}
+//! ```
+//!
+//! [`serve`] works on any reader and writer, [`serve_tcp`] accepts one controller at a time
+//! and drops it after [`READ_TIMEOUT`] without a request.
+//! [`serve_stdin`] and [`serve_tcp`] release every held key and button when a controller
+//! disconnects, so a `key_down` without its `key_up` does not stay pressed.
+//! ```no_run
+//! # use kemuler_windows::{Windows, server};
+//! server::serve_tcp(&mut Windows::new(), "127.0.0.1:7878").unwrap();
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, ToSocketAddrs},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    script::{self, Command},
+    TrySimulate, Windows,
+};
+
+/// Longest delay a request can ask for
+pub const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Longest request line in bytes, longer lines get an error reply
+pub const MAX_REQUEST_LENGTH: usize = 64 * 1024;
+
+/// How long [`serve_tcp`] waits for the next request before dropping the connection
+pub const READ_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// A decoded request line
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Request {
+    pub id: Option<u64>,
+    pub command: Command,
+}
+
+/// Result of a request
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reply {
+    pub id: Option<u64>,
+    pub result: Result<(), String>,
+}
+
+/// Error returned by [`decode`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DecodeError {
+    /// The line is not a valid request, with the JSON error message
+    Json(String),
+    UnknownKey(String),
+    UnknownButton(String),
+    /// A number is too large, with the name of the field
+    OutOfRange(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Json(message) => write!(f, "invalid request: {message}"),
+            DecodeError::UnknownKey(key) => write!(f, "unknown key `{key}`"),
+            DecodeError::UnknownButton(button) => write!(f, "unknown mouse button `{button}`"),
+            DecodeError::OutOfRange(field) => write!(f, "`{field}` is out of range"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+/// A request as it is written on the wire
+#[derive(Deserialize)]
+struct WireRequest {
+    #[serde(default)]
+    id: Option<u64>,
+    #[serde(flatten)]
+    command: WireCommand,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WireCommand {
+    KeyDown {
+        key: String,
+    },
+    KeyUp {
+        key: String,
+    },
+    Key {
+        keys: Vec<String>,
+    },
+    Text {
+        text: String,
+    },
+    MouseMove {
+        x: i32,
+        y: i32,
+    },
+    MouseButton {
+        button: String,
+        is_down: bool,
+    },
+    MouseScroll {
+        #[serde(default)]
+        vertical: i32,
+        #[serde(default)]
+        horizontal: i32,
+    },
+    Delay {
+        ms: u64,
+    },
+}
+
+/// Decode a request line.
+/// ```
+/// # use std::time::Duration;
+/// # use kemuler_windows::{VirtualKey, script::Command, server::*};
+/// let request = decode(r#"{"id": 3, "type": "key_down", "key": "LShift"}"#).unwrap();
+/// assert_eq!(request, Request { id: Some(3), command: Command::KeyDown(VirtualKey::LShift) });
+///
+/// let request = decode(r#"{"type": "delay", "ms": 250}"#).unwrap();
+/// assert_eq!(request, Request { id: None, command: Command::Wait(Duration::from_millis(250)) });
+///
+/// assert_eq!(decode(r#"{"type": "key_up", "key": "Shfit"}"#), Err(DecodeError::UnknownKey("Shfit".to_string())));
+/// assert!(matches!(decode(r#"{"type": "jump"}"#), Err(DecodeError::Json(_))));
+/// assert_eq!(decode(r#"{"type": "mouse_scroll", "vertical": 99999999}"#), Err(DecodeError::OutOfRange("vertical")));
+/// assert_eq!(decode(r#"{"type": "delay", "ms": 18446744073709551615}"#), Err(DecodeError::OutOfRange("ms")));
+/// ```
+pub fn decode(line: &str) -> Result<Request, DecodeError> {
+    let request: WireRequest =
+        serde_json::from_str(line).map_err(|error| DecodeError::Json(error.to_string()))?;
+    let key = |name: String| script::key_from_name(&name).ok_or(DecodeError::UnknownKey(name));
+    let command = match request.command {
+        WireCommand::KeyDown { key: name } => Command::KeyDown(key(name)?),
+        WireCommand::KeyUp { key: name } => Command::KeyUp(key(name)?),
+        WireCommand::Key { keys } => {
+            Command::Key(vec![keys.into_iter().map(key).collect::<Result<_, _>>()?])
+        }
+        WireCommand::Text { text } => Command::Type(text),
+        WireCommand::MouseMove { x, y } => Command::Move(x, y),
+        WireCommand::MouseButton { button, is_down } => {
+            let button =
+                script::button_from_name(&button).ok_or(DecodeError::UnknownButton(button))?;
+            if is_down {
+                Command::MouseDown(button)
+            } else {
+                Command::MouseUp(button)
+            }
+        }
+        WireCommand::MouseScroll {
+            vertical,
+            horizontal,
+        } => {
+            if Command::scroll(vertical, 0).is_none() {
+                return Err(DecodeError::OutOfRange("vertical"));
+            }
+            Command::scroll(vertical, horizontal).ok_or(DecodeError::OutOfRange("horizontal"))?
+        }
+        WireCommand::Delay { ms } => {
+            let duration = Duration::from_millis(ms);
+            if duration > MAX_DELAY {
+                return Err(DecodeError::OutOfRange("ms"));
+            }
+            Command::Wait(duration)
+        }
+    };
+    Ok(Request {
+        id: request.id,
+        command,
+    })
+}
+
+/// Encode a reply line without the trailing newline.
+/// ```
+/// # use kemuler_windows::server::*;
+/// assert_eq!(encode(&Reply { id: Some(1), result: Ok(()) }), r#"{"id":1,"ok":true}"#);
+/// assert_eq!(
+///     encode(&Reply { id: None, result: Err("no \"window\"".to_string()) }),
+///     r#"{"id":null,"ok":false,"error":"no \"window\""}"#
+/// );
+/// ```
+pub fn encode(reply: &Reply) -> String {
+    let id = serde_json::to_string(&reply.id).expect("id is serializable");
+    match &reply.result {
+        Ok(()) => format!(r#"{{"id":{id},"ok":true}}"#),
+        Err(error) => {
+            let error = serde_json::to_string(error).expect("string is serializable");
+            format!(r#"{{"id":{id},"ok":false,"error":{error}}}"#)
+        }
+    }
+}
+
+/// Decode a line, execute its command and return the reply.
+/// The id is recovered from lines that fail to decode when possible.
+/// ```
+/// # use kemuler_windows::{script::Command, server::*};
+/// let mut executed = Vec::new();
+/// let reply = handle_line(r#"{"id": 1, "type": "text", "text": "hi"}"#, |command| {
+///     executed.push(command);
+///     Ok::<_, String>(())
+/// });
+/// assert_eq!(reply, Reply { id: Some(1), result: Ok(()) });
+/// assert_eq!(executed, [Command::Type("hi".to_string())]);
+///
+/// let reply = handle_line(r#"{"id": 2, "type": "key_down"}"#, |_| Ok::<_, String>(()));
+/// assert_eq!(reply.id, Some(2));
+/// assert!(reply.result.is_err());
+/// # #[cfg(windows)] {
+/// # use kemuler_windows::Error;
+/// # use windows::Win32::Foundation::ERROR_ACCESS_DENIED;
+///
+/// // input blocked by UIPI
+/// let reply = handle_line(r#"{"id": 3, "type": "text", "text": "hi"}"#, |_| {
+///     Err(Error::Injection(windows::core::Error::from(ERROR_ACCESS_DENIED.to_hresult())))
+/// });
+/// assert!(encode(&reply).starts_with(r#"{"id":3,"ok":false,"error":"failed to inject input: "#));
+/// # }
+/// ```
+pub fn handle_line<E: fmt::Display>(
+    line: &str,
+    execute: impl FnOnce(Command) -> Result<(), E>,
+) -> Reply {
+    match decode(line) {
+        Ok(request) => Reply {
+            id: request.id,
+            result: execute(request.command).map_err(|error| error.to_string()),
+        },
+        Err(error) => {
+            #[derive(Deserialize)]
+            struct Id {
+                id: Option<u64>,
+            }
+            let id = serde_json::from_str::<Id>(line).ok().and_then(|id| id.id);
+            Reply {
+                id,
+                result: Err(error.to_string()),
+            }
+        }
+    }
+}
+
+/// Handle every line of `reader` and write the replies to `writer` until the end of input.
+/// Blank lines are skipped, lines longer than [`MAX_REQUEST_LENGTH`] get an error reply.
+/// ```
+/// # use kemuler_windows::{input_macro::Macro, script::{Script, Statement}, server::serve};
+/// let input = "{\"id\": 1, \"type\": \"key\", \"keys\": [\"ctrl\", \"c\"]}\n\n{\"id\": 2, \"type\": \"nope\"}\n";
+/// let mut output = Vec::new();
+/// let mut recorded = Macro::new();
+/// serve(input.as_bytes(), &mut output, |command| {
+///     Script { statements: vec![Statement { line: 1, command }] }.run(&mut recorded);
+///     Ok::<_, String>(())
+/// }).unwrap();
+/// let output = String::from_utf8(output).unwrap();
+/// let replies = output.lines().collect::<Vec<_>>();
+/// assert_eq!(replies.len(), 2);
+/// assert_eq!(replies[0], r#"{"id":1,"ok":true}"#);
+/// assert!(replies[1].starts_with(r#"{"id":2,"ok":false,"error":"invalid request"#));
+/// assert_eq!(recorded.events.len(), 4);
+///
+/// let input = format!("{}\n{{\"id\": 3, \"type\": \"delay\", \"ms\": 0}}\n", "x".repeat(100_000));
+/// let mut output = Vec::new();
+/// serve(input.as_bytes(), &mut output, |_| Ok::<_, String>(())).unwrap();
+/// let output = String::from_utf8(output).unwrap();
+/// let replies = output.lines().collect::<Vec<_>>();
+/// assert_eq!(replies, [
+///     r#"{"id":null,"ok":false,"error":"request longer than 65536 bytes"}"#,
+///     r#"{"id":3,"ok":true}"#,
+/// ]);
+/// ```
+pub fn serve<E: fmt::Display>(
+    mut reader: impl BufRead,
+    mut writer: impl Write,
+    mut execute: impl FnMut(Command) -> Result<(), E>,
+) -> io::Result<()> {
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let limit = MAX_REQUEST_LENGTH as u64 + 1;
+        if reader.by_ref().take(limit).read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        let reply = if line.len() > MAX_REQUEST_LENGTH && !line.ends_with(b"\n") {
+            reader.skip_until(b'\n')?;
+            Reply {
+                id: None,
+                result: Err(format!("request longer than {MAX_REQUEST_LENGTH} bytes")),
+            }
+        } else {
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            handle_line(line, &mut execute)
+        };
+        writeln!(writer, "{}", encode(&reply))?;
+        writer.flush()?;
+    }
+}
+
+/// Execute commands from standard input on `windows`, replying on standard output.
+/// Everything held is released at the end of input.
+pub fn serve_stdin(windows: &mut Windows) -> io::Result<()> {
+    let result = serve(io::stdin().lock(), io::stdout().lock(), |command| {
+        windows.try_simulate(command)
+    });
+    windows.release_all();
+    result
+}
+
+/// Execute commands from TCP connections on `windows`, one connection at a time.
+///
+/// Only loopback addresses are accepted since anyone who can connect can control the machine.
+/// Everything held is released when a connection ends.
+/// A failing connection, or one without a request for [`READ_TIMEOUT`], is dropped,
+/// only errors of the listener are returned.
+pub fn serve_tcp(windows: &mut Windows, address: impl ToSocketAddrs) -> io::Result<()> {
+    let addresses = address.to_socket_addrs()?.collect::<Vec<_>>();
+    if let Some(address) = addresses.iter().find(|address| !address.ip().is_loopback()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("refusing to listen on non-loopback address {address}"),
+        ));
+    }
+    let listener = TcpListener::bind(&addresses[..])?;
+    for stream in listener.incoming() {
+        let stream = stream?;
+        // a controller disconnecting, stalling or sending garbage should not stop the server
+        let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+        let _ = stream.try_clone().and_then(|reader| {
+            serve(BufReader::new(reader), stream, |command| {
+                windows.try_simulate(command)
+            })
+        });
+        windows.release_all();
+    }
+    Ok(())
+}
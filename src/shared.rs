@@ -0,0 +1,175 @@
+//! Share one simulator between threads.
+//!
+//! Cloning a [`Windows`] gives every clone its own record of held keys and pen state,
+//! so producers on different threads should share one instance through [`Shared`] instead.
+//! Every call locks the simulator, so events from one producer keep their order
+//! and a [`Shared::batch`] is never interleaved with events from other producers.
+//! If a producer panics while holding the lock, the next caller releases everything
+//! the simulator holds before using it, see [`Release`].
+//! ```
+//! # use std::thread;
+//! # use kemuler::prelude::*;
+//! # use kemuler_windows::{VirtualKey, input_macro::{Macro, MacroEvent}, shared::Shared};
+//! fn assert_send_sync<T: Send + Sync>() {}
+//! assert_send_sync::<Shared>();
+//!
+//! let shared = Shared::new(Macro::new());
+//! let producers = [VirtualKey::A, VirtualKey::B, VirtualKey::C].map(|key| {
+//!     let shared = shared.clone();
+//!     thread::spawn(move || {
+//!         for _ in 0..50 {
+//!             shared.batch(|recorded| {
+//!                 recorded.simulate(key.down());
+//!                 recorded.simulate(key.up());
+//!             });
+//!         }
+//!     })
+//! });
+//! producers.into_iter().for_each(|producer| producer.join().unwrap());
+//!
+//! let recorded = shared.batch(|recorded| recorded.clone());
+//! assert_eq!(recorded.events.len(), 300);
+//! for pair in recorded.events.chunks(2) {
+//!     let (MacroEvent::Key { key: down, is_down: true }, MacroEvent::Key { key: up, is_down: false }) =
+//!         (pair[0].event, pair[1].event)
+//!     else {
+//!         panic!("batch was interleaved");
+//!     };
+//!     assert_eq!(down, up);
+//! }
+//! ```
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use kemuler::simulator::Simulate;
+
+use crate::{input_macro::Macro, Error, TrySimulate, Windows};
+
+/// A simulator that can release every key and button it holds
+/// ```
+/// # use std::thread;
+/// # use kemuler::prelude::*;
+/// # use kemuler_windows::{VirtualKey, Windows, dry_run::Recording, shared::Shared};
+/// let log = Recording::new();
+/// let shared = Shared::new(Windows::new().with_signature(0).with_dry_run(log.clone()));
+/// let producer = shared.clone();
+/// let panicked = thread::spawn(move || {
+///     producer.batch(|windows| {
+///         windows.simulate(VirtualKey::LShift.down());
+///         panic!("producer failed while holding shift");
+///     })
+/// });
+/// assert!(panicked.join().is_err());
+///
+/// shared.simulate(VirtualKey::A.down());
+/// assert!(log.lines()[1].starts_with("KEYUP VK_LSHIFT"));
+/// assert!(log.lines()[2].starts_with("KEYDOWN VK_A"));
+/// ```
+pub trait Release {
+    fn release_all(&mut self);
+}
+
+impl Release for Windows {
+    fn release_all(&mut self) {
+        Windows::release_all(self)
+    }
+}
+
+/// A recording holds nothing, events already recorded are kept as they are
+impl Release for Macro {
+    fn release_all(&mut self) {}
+}
+
+/// A simulator that can be cloned and sent to other threads, every clone uses the same simulator
+#[derive(Debug, Default)]
+pub struct Shared<T = Windows> {
+    simulator: Arc<Mutex<T>>,
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Shared {
+            simulator: Arc::clone(&self.simulator),
+        }
+    }
+}
+
+impl<T> Shared<T> {
+    pub fn new(simulator: T) -> Shared<T> {
+        Shared {
+            simulator: Arc::new(Mutex::new(simulator)),
+        }
+    }
+}
+
+impl<T: Release> Shared<T> {
+    /// Wait until no other producer is simulating
+    /// and run `f` with exclusive access to the simulator.
+    ///
+    /// Events simulated inside `f` are not interleaved with events from other producers.
+    /// ```no_run
+    /// # use kemuler::prelude::*;
+    /// # use kemuler_windows::{VirtualKey, Windows, shared::Shared};
+    /// let shared = Shared::new(Windows::new());
+    /// shared.batch(|windows| {
+    ///     windows.simulate(VirtualKey::Control.down());
+    ///     windows.simulate(VirtualKey::V.down());
+    ///     windows.simulate(VirtualKey::V.up());
+    ///     windows.simulate(VirtualKey::Control.up());
+    /// });
+    /// ```
+    pub fn batch<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.lock())
+    }
+
+    /// Simulate one input
+    pub fn simulate<S>(&self, simulatable: S)
+    where
+        T: Simulate<S>,
+    {
+        self.lock().simulate(simulatable)
+    }
+
+    /// Simulate one input, returning the error instead of panicking
+    pub fn try_simulate<S>(&self, simulatable: S) -> Result<(), Error>
+    where
+        T: TrySimulate<S>,
+    {
+        self.lock().try_simulate(simulatable)
+    }
+
+    /// A producer that panicked while simulating does not make the simulator unusable,
+    /// what it was holding is released
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.simulator.lock().unwrap_or_else(|error| {
+            let mut simulator = error.into_inner();
+            simulator.release_all();
+            self.simulator.clear_poison();
+            simulator
+        })
+    }
+}
+
+impl<T> From<T> for Shared<T> {
+    fn from(simulator: T) -> Self {
+        Shared::new(simulator)
+    }
+}
+
+impl<T, S> Simulate<S> for Shared<T>
+where
+    T: Simulate<S> + Release,
+{
+    fn simulate(&mut self, simulatable: S) {
+        Shared::simulate(self, simulatable)
+    }
+}
+
+impl<T, S> TrySimulate<S> for Shared<T>
+where
+    T: TrySimulate<S> + Release,
+{
+    fn try_simulate(&mut self, simulatable: S) -> Result<(), Error> {
+        Shared::try_simulate(self, simulatable)
+    }
+}
@@ -0,0 +1,525 @@
+//! Delays between events and minimum key hold durations.
+//!
+//! Many applications drop input when a key goes down and up in the same millisecond.
+//! A [`Timing`] set on [`Windows`](crate::Windows) spaces out every event it sends
+//! and keeps keys and buttons down for a minimum duration.
+//! ```no_run
+//! # use std::time::Duration;
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{VirtualKey, Windows, timing::*};
+//! let mut windows = Windows::new().with_timing(
+//!     Timing::new()
+//!         .with_min_hold(Duration::from_millis(30))
+//!         .with_between_events(Duration::from_millis(5)),
+//! );
+//! windows.simulate(VirtualKey::A.down());
+//! // waits until A was held for 30ms
+//! windows.simulate(VirtualKey::A.up());
+//! // overrides the timing for one call
+//! windows.simulate(Timed::new(VirtualKey::B.down(), Timing::new()));
+//! ```
+//!
+//! Time is read and waited on through a [`Clock`], use a [`ManualClock`] to test timing
+//! without waiting.
+//! [`Timing::per_char`] applies once per character,
+//! even for characters typed as two UTF-16 surrogates.
+//! ```
+//! # use std::{sync::Arc, time::Duration};
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler::{common_inputs::Char, input_event::SetTo};
+//! # use kemuler_windows::{Windows, dry_run::Recording, timing::*};
+//! let clock = Arc::new(ManualClock::new());
+//! let log = Recording::new();
+//! let mut windows = Windows::new()
+//!     .with_signature(0)
+//!     .with_dry_run(log.clone())
+//!     .with_timing(Timing::new().with_per_char(Duration::from_millis(50)))
+//!     .with_clock(clock.clone());
+//! for char in ['😀', '😀'] {
+//!     windows.simulate(SetTo::new(Char(char), true));
+//!     windows.simulate(SetTo::new(Char(char), false));
+//! }
+//! assert_eq!(log.lines().len(), 8);
+//! assert_eq!(clock.sleeps(), [Duration::from_millis(50)]);
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, PoisonError, Weak},
+    thread,
+    time::{Duration, Instant},
+};
+
+use windows::Win32::UI::{Input::KeyboardAndMouse, WindowsAndMessaging};
+
+use crate::{
+    inner::{self, send_input::WindowsSendInputEnum},
+    MouseButton,
+};
+
+/// Source of time for [`Pacer`].
+///
+/// [`SystemClock`] uses the real time, [`ManualClock`] is useful for testing.
+pub trait Clock: Send + Sync {
+    /// Time elapsed since an arbitrary fixed point
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+}
+
+/// Real time with `Instant` and `thread::sleep`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration)
+    }
+}
+
+/// Clock that only moves when slept on or advanced, and remembers every sleep
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: Mutex<Duration>,
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    /// Move time forward without recording a sleep
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    /// Every duration slept so far
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.now.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+        self.sleeps.lock().unwrap().push(duration);
+    }
+}
+
+impl<C> Clock for Arc<C>
+where
+    C: Clock + ?Sized,
+{
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration)
+    }
+}
+
+/// Timing policy, every duration is a minimum and defaults to zero
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Timing {
+    /// Time between a key or button going down and going up
+    pub min_hold: Duration,
+    /// Time between two consecutive events
+    pub between_events: Duration,
+    /// Time between two typed characters
+    pub per_char: Duration,
+}
+
+impl Timing {
+    pub fn new() -> Timing {
+        Timing::default()
+    }
+
+    pub fn with_min_hold(mut self, min_hold: Duration) -> Timing {
+        self.min_hold = min_hold;
+        self
+    }
+
+    pub fn with_between_events(mut self, between_events: Duration) -> Timing {
+        self.between_events = between_events;
+        self
+    }
+
+    pub fn with_per_char(mut self, per_char: Duration) -> Timing {
+        self.per_char = per_char;
+        self
+    }
+
+    /// Return true if no event is ever delayed
+    pub fn is_zero(&self) -> bool {
+        self.min_hold.is_zero() && self.between_events.is_zero() && self.per_char.is_zero()
+    }
+}
+
+/// Simulate `simulatable` with `timing` instead of the simulator's timing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timed<S> {
+    pub simulatable: S,
+    pub timing: Timing,
+}
+
+impl<S> Timed<S> {
+    pub fn new(simulatable: S, timing: Timing) -> Timed<S> {
+        Timed {
+            simulatable,
+            timing,
+        }
+    }
+}
+
+/// Something that is held down between a [`PacedEvent::Down`] and a [`PacedEvent::Up`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Held {
+    /// Virtual key code
+    Key(u16),
+    /// Character typed with unicode input
+    Char(char),
+    MouseButton(MouseButton),
+}
+
+impl Held {
+    /// Events that release it
+    pub(crate) fn release(self) -> Vec<WindowsSendInputEnum> {
+        match self {
+            Held::Key(code) => vec![WindowsSendInputEnum::Keyboard {
+                wVk: KeyboardAndMouse::VIRTUAL_KEY(code),
+                wScan: 0,
+                dwFlags: KeyboardAndMouse::KEYEVENTF_KEYUP,
+            }],
+            Held::Char(char) => inner::send_input::char_key_up(char),
+            Held::MouseButton(button) => inner::send_input::mouse_button_up(button),
+        }
+    }
+}
+
+/// An event as seen by a [`Pacer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacedEvent {
+    Down(Held),
+    Up(Held),
+    /// Anything else, like a mouse move or scroll
+    Other,
+}
+
+impl PacedEvent {
+    /// Events of `inputs` with the number of inputs each spans.
+    /// A character outside the BMP is typed as two inputs, one per surrogate,
+    /// which are paced as one event.
+    pub(crate) fn from_send_inputs(inputs: &[WindowsSendInputEnum]) -> Vec<(PacedEvent, usize)> {
+        let mut events = Vec::new();
+        let mut index = 0;
+        while let Some(input) = inputs.get(index) {
+            let length = match (
+                unicode_unit(input),
+                inputs.get(index + 1).and_then(unicode_unit),
+            ) {
+                (Some((high, is_up)), Some((low, next_is_up)))
+                    if is_up == next_is_up
+                        && (0xD800..0xDC00).contains(&high)
+                        && (0xDC00..0xE000).contains(&low) =>
+                {
+                    2
+                }
+                _ => 1,
+            };
+            let event = match unicode_unit(input) {
+                Some((_, is_up)) => {
+                    let units = inputs[index..index + length]
+                        .iter()
+                        .filter_map(|input| unicode_unit(input).map(|(unit, _)| unit));
+                    let char = char::decode_utf16(units)
+                        .next()
+                        .and_then(Result::ok)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    if is_up {
+                        PacedEvent::Up(Held::Char(char))
+                    } else {
+                        PacedEvent::Down(Held::Char(char))
+                    }
+                }
+                None => PacedEvent::from_send_input(input),
+            };
+            events.push((event, length));
+            index += length;
+        }
+        events
+    }
+
+    fn from_send_input(input: &WindowsSendInputEnum) -> PacedEvent {
+        match *input {
+            WindowsSendInputEnum::Keyboard { wVk, dwFlags, .. } => {
+                let held = Held::Key(wVk.0);
+                if (dwFlags & KeyboardAndMouse::KEYEVENTF_KEYUP).0 != 0 {
+                    PacedEvent::Up(held)
+                } else {
+                    PacedEvent::Down(held)
+                }
+            }
+            WindowsSendInputEnum::Mouse {
+                mouseData, dwFlags, ..
+            } => {
+                let is_flag = |flag: KeyboardAndMouse::MOUSE_EVENT_FLAGS| (dwFlags & flag).0 != 0;
+                let x_button = if mouseData as u16 == WindowsAndMessaging::XBUTTON1 {
+                    MouseButton::X1
+                } else {
+                    MouseButton::X2
+                };
+                let buttons = [
+                    (
+                        MouseButton::Left,
+                        KeyboardAndMouse::MOUSEEVENTF_LEFTDOWN,
+                        KeyboardAndMouse::MOUSEEVENTF_LEFTUP,
+                    ),
+                    (
+                        MouseButton::Middle,
+                        KeyboardAndMouse::MOUSEEVENTF_MIDDLEDOWN,
+                        KeyboardAndMouse::MOUSEEVENTF_MIDDLEUP,
+                    ),
+                    (
+                        MouseButton::Right,
+                        KeyboardAndMouse::MOUSEEVENTF_RIGHTDOWN,
+                        KeyboardAndMouse::MOUSEEVENTF_RIGHTUP,
+                    ),
+                    (
+                        x_button,
+                        KeyboardAndMouse::MOUSEEVENTF_XDOWN,
+                        KeyboardAndMouse::MOUSEEVENTF_XUP,
+                    ),
+                ];
+                buttons
+                    .into_iter()
+                    .find_map(|(button, down, up)| {
+                        if is_flag(down) {
+                            Some(PacedEvent::Down(Held::MouseButton(button)))
+                        } else if is_flag(up) {
+                            Some(PacedEvent::Up(Held::MouseButton(button)))
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or(PacedEvent::Other)
+            }
+            WindowsSendInputEnum::Hardware { .. } => PacedEvent::Other,
+        }
+    }
+}
+
+/// UTF-16 code unit of a unicode keyboard input and whether it is a key up
+fn unicode_unit(input: &WindowsSendInputEnum) -> Option<(u16, bool)> {
+    match *input {
+        WindowsSendInputEnum::Keyboard { wScan, dwFlags, .. }
+            if (dwFlags & KeyboardAndMouse::KEYEVENTF_UNICODE).0 != 0 =>
+        {
+            Some((wScan, (dwFlags & KeyboardAndMouse::KEYEVENTF_KEYUP).0 != 0))
+        }
+        _ => None,
+    }
+}
+
+/// Wait before events according to a [`Timing`].
+/// ```
+/// # use std::{sync::Arc, time::Duration};
+/// # use kemuler_windows::timing::*;
+/// let clock = Arc::new(ManualClock::new());
+/// let mut pacer = Pacer::new(
+///     Timing::new()
+///         .with_min_hold(Duration::from_millis(30))
+///         .with_between_events(Duration::from_millis(5))
+///         .with_per_char(Duration::from_millis(50)),
+/// )
+/// .with_clock(clock.clone());
+///
+/// pacer.wait_before(PacedEvent::Down(Held::Key(0x41)));
+/// pacer.wait_before(PacedEvent::Other);
+/// clock.advance(Duration::from_millis(10));
+/// pacer.wait_before(PacedEvent::Up(Held::Key(0x41)));
+/// pacer.wait_before(PacedEvent::Down(Held::Char('a')));
+/// assert_eq!(clock.sleeps(), [
+///     Duration::from_millis(5),
+///     Duration::from_millis(15),
+///     Duration::from_millis(50),
+/// ]);
+/// ```
+pub struct Pacer {
+    timing: Timing,
+    clock: Arc<dyn Clock>,
+    last_event: Option<Duration>,
+    /// Shared with [`HeldHandle`]s so held input can be released from other threads
+    held_since: Arc<Mutex<HashMap<Held, Duration>>>,
+}
+
+impl Clone for Pacer {
+    /// Clones track what they hold separately
+    fn clone(&self) -> Self {
+        Pacer {
+            timing: self.timing,
+            clock: Arc::clone(&self.clock),
+            last_event: self.last_event,
+            held_since: Arc::new(Mutex::new(self.held_since().clone())),
+        }
+    }
+}
+
+impl fmt::Debug for Pacer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pacer")
+            .field("timing", &self.timing)
+            .field("last_event", &self.last_event)
+            .field("held_since", &*self.held_since())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Pacer::new(Timing::default())
+    }
+}
+
+impl Pacer {
+    pub fn new(timing: Timing) -> Pacer {
+        Pacer {
+            timing,
+            clock: Arc::new(SystemClock::default()),
+            last_event: None,
+            held_since: Arc::default(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Pacer {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// How long to wait before `event` if it happens at `now`.
+    /// The event is recorded as happening after the delay.
+    pub fn delay_before(&mut self, event: PacedEvent, now: Duration) -> Duration {
+        let ready = self.ready_at(event, now);
+        match event {
+            PacedEvent::Down(held) => {
+                self.held_since().insert(held, ready);
+            }
+            PacedEvent::Up(held) => {
+                self.held_since().remove(&held);
+            }
+            PacedEvent::Other => {}
+        }
+        self.last_event = Some(ready);
+        ready - now
+    }
+
+    /// When `event` can happen if it is asked for at `now`, nothing is recorded
+    fn ready_at(&self, event: PacedEvent, now: Duration) -> Duration {
+        let mut ready = now;
+        if let Some(last_event) = self.last_event {
+            ready = ready.max(last_event + self.timing.between_events);
+            if let PacedEvent::Down(Held::Char(_)) = event {
+                ready = ready.max(last_event + self.timing.per_char);
+            }
+        }
+        if let PacedEvent::Up(held) = event {
+            if let Some(&since) = self.held_since().get(&held) {
+                ready = ready.max(since + self.timing.min_hold);
+            }
+        }
+        ready
+    }
+
+    /// Everything currently held down
+    pub fn held(&self) -> Vec<Held> {
+        self.held_since().keys().copied().collect()
+    }
+
+    /// Forget everything held down and return it
+    pub fn take_held(&mut self) -> Vec<Held> {
+        self.held_since().drain().map(|(held, _)| held).collect()
+    }
+
+    /// Forget that `held` is held down, return whether it was
+    pub(crate) fn forget(&mut self, held: Held) -> bool {
+        self.held_since().remove(&held).is_some()
+    }
+
+    /// Handle that takes what this pacer holds from another thread
+    pub(crate) fn held_handle(&self) -> HeldHandle {
+        HeldHandle(Arc::downgrade(&self.held_since))
+    }
+
+    fn held_since(&self) -> std::sync::MutexGuard<'_, HashMap<Held, Duration>> {
+        self.held_since
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Sleep on the clock for `duration`.
+    pub fn sleep(&self, duration: Duration) {
+        if !duration.is_zero() {
+            self.clock.sleep(duration);
+        }
+    }
+
+    /// Sleep on the clock until `event` can happen.
+    pub fn wait_before(&mut self, event: PacedEvent) {
+        let delay = self.delay_before(event, self.clock.now());
+        self.sleep(delay);
+    }
+
+    /// Sleep on the clock until `event` can happen without recording it,
+    /// so the caller can still decide not to send it
+    pub(crate) fn wait_until_ready(&self, event: PacedEvent) {
+        let now = self.clock.now();
+        self.sleep(self.ready_at(event, now) - now);
+    }
+
+    /// Record `event` as happening now, or when it is ready if that is later
+    pub(crate) fn record(&mut self, event: PacedEvent) {
+        self.delay_before(event, self.clock.now());
+    }
+}
+
+/// What a [`Pacer`] holds, usable after the pacer is gone
+#[derive(Debug, Clone)]
+pub(crate) struct HeldHandle(Weak<Mutex<HashMap<Held, Duration>>>);
+
+impl HeldHandle {
+    /// Forget everything held down and return it, nothing if the pacer is gone
+    pub(crate) fn take(&self) -> Vec<Held> {
+        let Some(held_since) = self.0.upgrade() else {
+            return Vec::new();
+        };
+        let mut held_since = held_since.lock().unwrap_or_else(PoisonError::into_inner);
+        held_since.drain().map(|(held, _)| held).collect()
+    }
+}
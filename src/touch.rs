@@ -0,0 +1,405 @@
+//! Synthetic touch input with `InjectTouchInput`.
+//!
+//! A [`TouchFrame`] holds the state of every active [`TouchContact`] at one point in time
+//! and is injected as a whole. A contact goes down in one frame, is updated in the following frames
+//! and goes up in the last frame it appears in.
+//! ```no_run
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{Windows, touch::*};
+//! let mut windows = Windows::new();
+//! windows.simulate(TouchFrame::new([TouchContact::down(0, (500, 300))]));
+//! windows.simulate(TouchFrame::new([TouchContact::update(0, (520, 300))]));
+//! windows.simulate(TouchFrame::new([TouchContact::up(0, (520, 300))]));
+//! ```
+//!
+//! [`Gesture`]s generate the frames of common gestures.
+//! ```no_run
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{Windows, touch::*};
+//! let mut windows = Windows::new();
+//! windows.simulate(Gesture::Pinch {
+//!     center: (500, 300),
+//!     from_distance: 300,
+//!     to_distance: 100,
+//!     steps: 10,
+//! });
+//! ```
+
+use std::{error::Error, fmt, time::Duration};
+
+use windows::Win32::{
+    Foundation::{POINT, RECT},
+    UI::{Input::Pointer, WindowsAndMessaging},
+};
+
+use crate::inner;
+
+/// Maximum number of contacts in a [`TouchFrame`]
+pub const MAX_CONTACTS: usize = inner::pointer::MAX_TOUCH_CONTACTS as usize;
+
+/// Maximum [`TouchContact::pressure`]
+pub const MAX_PRESSURE: u32 = 1024;
+
+/// Time between frames when simulating a [`Gesture`]
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Distance between the two fingers of [`Gesture::TwoFingerScroll`] in pixels
+pub const TWO_FINGER_SPACING: i32 = 40;
+
+/// What happens to a [`TouchContact`] in a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchPhase {
+    /// The contact touches the screen
+    Down,
+    /// The contact stays on the screen and may move
+    Update,
+    /// The contact leaves the screen
+    Up,
+}
+
+/// One finger on the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TouchContact {
+    /// Identifies the contact across frames, must be unique within a frame
+    pub id: u32,
+    /// Position in virtual desktop pixels
+    pub position: (i32, i32),
+    pub phase: TouchPhase,
+    /// Width and height of the contact area centered on the position
+    pub contact_size: Option<(u32, u32)>,
+    /// Pressure from 0 to [`MAX_PRESSURE`]
+    pub pressure: Option<u32>,
+    /// Angle in degrees from 0 to 359, 0 is pointing up
+    pub orientation: Option<u32>,
+}
+
+impl TouchContact {
+    pub fn new(id: u32, position: (i32, i32), phase: TouchPhase) -> TouchContact {
+        TouchContact {
+            id,
+            position,
+            phase,
+            contact_size: None,
+            pressure: None,
+            orientation: None,
+        }
+    }
+
+    pub fn down(id: u32, position: (i32, i32)) -> TouchContact {
+        TouchContact::new(id, position, TouchPhase::Down)
+    }
+
+    pub fn update(id: u32, position: (i32, i32)) -> TouchContact {
+        TouchContact::new(id, position, TouchPhase::Update)
+    }
+
+    pub fn up(id: u32, position: (i32, i32)) -> TouchContact {
+        TouchContact::new(id, position, TouchPhase::Up)
+    }
+
+    pub fn with_contact_size(mut self, width: u32, height: u32) -> TouchContact {
+        self.contact_size = Some((width, height));
+        self
+    }
+
+    pub fn with_pressure(mut self, pressure: u32) -> TouchContact {
+        self.pressure = Some(pressure);
+        self
+    }
+
+    pub fn with_orientation(mut self, orientation: u32) -> TouchContact {
+        self.orientation = Some(orientation);
+        self
+    }
+
+    /// Convert into the structure passed to `InjectTouchInput`
+    /// ```
+    /// # use kemuler_windows::touch::*;
+    /// # use windows::Win32::UI::{Input::Pointer::*, WindowsAndMessaging::*};
+    /// let info = TouchContact::down(1, (100, 200))
+    ///     .with_contact_size(4, 6)
+    ///     .with_pressure(512)
+    ///     .into_windows();
+    /// assert_eq!(info.pointerInfo.pointerId, 1);
+    /// assert_eq!(
+    ///     info.pointerInfo.pointerFlags,
+    ///     POINTER_FLAG_DOWN | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT
+    /// );
+    /// assert_eq!((info.pointerInfo.ptPixelLocation.x, info.pointerInfo.ptPixelLocation.y), (100, 200));
+    /// assert_eq!(info.touchMask, TOUCH_MASK_CONTACTAREA | TOUCH_MASK_PRESSURE);
+    /// assert_eq!(
+    ///     (info.rcContact.left, info.rcContact.top, info.rcContact.right, info.rcContact.bottom),
+    ///     (98, 197, 102, 203)
+    /// );
+    /// assert_eq!(info.pressure, 512);
+    ///
+    /// let info = TouchContact::up(1, (100, 200)).into_windows();
+    /// assert_eq!(info.pointerInfo.pointerFlags, POINTER_FLAG_UP);
+    /// assert_eq!(info.touchMask, TOUCH_MASK_NONE);
+    /// ```
+    pub fn into_windows(self) -> Pointer::POINTER_TOUCH_INFO {
+        let pointer_flags = match self.phase {
+            TouchPhase::Down => {
+                Pointer::POINTER_FLAG_DOWN
+                    | Pointer::POINTER_FLAG_INRANGE
+                    | Pointer::POINTER_FLAG_INCONTACT
+            }
+            TouchPhase::Update => {
+                Pointer::POINTER_FLAG_UPDATE
+                    | Pointer::POINTER_FLAG_INRANGE
+                    | Pointer::POINTER_FLAG_INCONTACT
+            }
+            TouchPhase::Up => Pointer::POINTER_FLAG_UP,
+        };
+        let (x, y) = self.position;
+        let mut info = Pointer::POINTER_TOUCH_INFO {
+            pointerInfo: Pointer::POINTER_INFO {
+                pointerType: WindowsAndMessaging::PT_TOUCH,
+                pointerId: self.id,
+                pointerFlags: pointer_flags,
+                ptPixelLocation: POINT { x, y },
+                ..Default::default()
+            },
+            touchMask: WindowsAndMessaging::TOUCH_MASK_NONE,
+            ..Default::default()
+        };
+        if let Some((width, height)) = self.contact_size {
+            let (width, height) = (width as i32, height as i32);
+            info.touchMask |= WindowsAndMessaging::TOUCH_MASK_CONTACTAREA;
+            info.rcContact = RECT {
+                left: x - width / 2,
+                top: y - height / 2,
+                right: x - width / 2 + width,
+                bottom: y - height / 2 + height,
+            };
+        }
+        if let Some(orientation) = self.orientation {
+            info.touchMask |= WindowsAndMessaging::TOUCH_MASK_ORIENTATION;
+            info.orientation = orientation;
+        }
+        if let Some(pressure) = self.pressure {
+            info.touchMask |= WindowsAndMessaging::TOUCH_MASK_PRESSURE;
+            info.pressure = pressure;
+        }
+        info
+    }
+}
+
+/// Error returned by [`TouchFrame::validate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchError {
+    /// The frame has no contacts
+    Empty,
+    /// The frame has more than [`MAX_CONTACTS`] contacts
+    TooManyContacts(usize),
+    /// More than one contact in the frame has this id
+    DuplicateId(u32),
+    /// The pressure is more than [`MAX_PRESSURE`]
+    PressureOutOfRange(u32),
+    /// The orientation is not between 0 and 359
+    OrientationOutOfRange(u32),
+}
+
+impl fmt::Display for TouchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TouchError::Empty => write!(f, "touch frame has no contacts"),
+            TouchError::TooManyContacts(count) => write!(
+                f,
+                "touch frame has {count} contacts, at most {MAX_CONTACTS} are supported"
+            ),
+            TouchError::DuplicateId(id) => {
+                write!(f, "touch frame has more than one contact with id {id}")
+            }
+            TouchError::PressureOutOfRange(pressure) => {
+                write!(f, "touch pressure {pressure} is more than {MAX_PRESSURE}")
+            }
+            TouchError::OrientationOutOfRange(orientation) => {
+                write!(
+                    f,
+                    "touch orientation {orientation} is not between 0 and 359"
+                )
+            }
+        }
+    }
+}
+
+impl Error for TouchError {}
+
+/// State of every active contact at one point in time
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct TouchFrame {
+    pub contacts: Vec<TouchContact>,
+}
+
+impl TouchFrame {
+    pub fn new(contacts: impl IntoIterator<Item = TouchContact>) -> TouchFrame {
+        TouchFrame {
+            contacts: contacts.into_iter().collect(),
+        }
+    }
+
+    /// Check that `InjectTouchInput` can accept the frame.
+    /// ```
+    /// # use kemuler_windows::touch::*;
+    /// let frame = TouchFrame::new([TouchContact::down(0, (0, 0)), TouchContact::down(1, (10, 0))]);
+    /// assert_eq!(frame.validate(), Ok(()));
+    ///
+    /// let frame = TouchFrame::new([TouchContact::down(0, (0, 0)), TouchContact::down(0, (10, 0))]);
+    /// assert_eq!(frame.validate(), Err(TouchError::DuplicateId(0)));
+    ///
+    /// let frame = TouchFrame::new([TouchContact::down(0, (0, 0)).with_pressure(2000)]);
+    /// assert_eq!(frame.validate(), Err(TouchError::PressureOutOfRange(2000)));
+    ///
+    /// assert_eq!(TouchFrame::default().validate(), Err(TouchError::Empty));
+    /// ```
+    pub fn validate(&self) -> Result<(), TouchError> {
+        if self.contacts.is_empty() {
+            return Err(TouchError::Empty);
+        }
+        if self.contacts.len() > MAX_CONTACTS {
+            return Err(TouchError::TooManyContacts(self.contacts.len()));
+        }
+        for (i, contact) in self.contacts.iter().enumerate() {
+            if self.contacts[..i]
+                .iter()
+                .any(|other| other.id == contact.id)
+            {
+                return Err(TouchError::DuplicateId(contact.id));
+            }
+            if let Some(pressure) = contact.pressure.filter(|&p| p > MAX_PRESSURE) {
+                return Err(TouchError::PressureOutOfRange(pressure));
+            }
+            if let Some(orientation) = contact.orientation.filter(|&o| o > 359) {
+                return Err(TouchError::OrientationOutOfRange(orientation));
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert into the structures passed to `InjectTouchInput`
+    pub fn into_windows(&self) -> Vec<Pointer::POINTER_TOUCH_INFO> {
+        self.contacts
+            .iter()
+            .map(|contact| contact.into_windows())
+            .collect()
+    }
+}
+
+/// Common touch gestures, simulated as a sequence of [`TouchFrame`]s
+/// [`FRAME_INTERVAL`] apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gesture {
+    /// Touch and release with one finger
+    Tap { position: (i32, i32) },
+    /// Drag two fingers side by side, [`TWO_FINGER_SPACING`] apart, centered on `position`
+    TwoFingerScroll {
+        position: (i32, i32),
+        delta: (i32, i32),
+        steps: u32,
+    },
+    /// Move two fingers horizontally from `from_distance` apart to `to_distance` apart.
+    /// Pinch in by moving them closer, zoom out by moving them apart.
+    Pinch {
+        center: (i32, i32),
+        from_distance: i32,
+        to_distance: i32,
+        steps: u32,
+    },
+}
+
+impl Gesture {
+    /// Frames that make up the gesture.
+    /// Moving gestures have a down frame, `steps` update frames and an up frame.
+    /// A moving gesture with zero `steps` still moves, in a single update frame.
+    /// ```
+    /// # use kemuler_windows::touch::*;
+    /// let frames = Gesture::Tap { position: (10, 20) }.frames();
+    /// assert_eq!(frames, [
+    ///     TouchFrame::new([TouchContact::down(0, (10, 20))]),
+    ///     TouchFrame::new([TouchContact::up(0, (10, 20))]),
+    /// ]);
+    ///
+    /// let frames = Gesture::TwoFingerScroll { position: (100, 100), delta: (0, -50), steps: 2 }.frames();
+    /// assert_eq!(frames, [
+    ///     TouchFrame::new([TouchContact::down(0, (80, 100)), TouchContact::down(1, (120, 100))]),
+    ///     TouchFrame::new([TouchContact::update(0, (80, 75)), TouchContact::update(1, (120, 75))]),
+    ///     TouchFrame::new([TouchContact::update(0, (80, 50)), TouchContact::update(1, (120, 50))]),
+    ///     TouchFrame::new([TouchContact::up(0, (80, 50)), TouchContact::up(1, (120, 50))]),
+    /// ]);
+    ///
+    /// let frames = Gesture::Pinch { center: (100, 100), from_distance: 100, to_distance: 20, steps: 1 }.frames();
+    /// assert_eq!(frames, [
+    ///     TouchFrame::new([TouchContact::down(0, (50, 100)), TouchContact::down(1, (150, 100))]),
+    ///     TouchFrame::new([TouchContact::update(0, (90, 100)), TouchContact::update(1, (110, 100))]),
+    ///     TouchFrame::new([TouchContact::up(0, (90, 100)), TouchContact::up(1, (110, 100))]),
+    /// ]);
+    ///
+    /// let frames = Gesture::TwoFingerScroll { position: (0, i32::MAX - 20), delta: (0, i32::MIN), steps: 0 }.frames();
+    /// assert_eq!(frames, [
+    ///     TouchFrame::new([TouchContact::down(0, (-20, i32::MAX - 20)), TouchContact::down(1, (20, i32::MAX - 20))]),
+    ///     TouchFrame::new([TouchContact::update(0, (-20, -21)), TouchContact::update(1, (20, -21))]),
+    ///     TouchFrame::new([TouchContact::up(0, (-20, -21)), TouchContact::up(1, (20, -21))]),
+    /// ]);
+    /// ```
+    pub fn frames(&self) -> Vec<TouchFrame> {
+        match *self {
+            Gesture::Tap { position } => vec![
+                TouchFrame::new([TouchContact::down(0, position)]),
+                TouchFrame::new([TouchContact::up(0, position)]),
+            ],
+            Gesture::TwoFingerScroll {
+                position: (x, y),
+                delta: (dx, dy),
+                steps,
+            } => two_finger_frames(steps, |step| {
+                let (x, y) = (
+                    x.saturating_add(lerp(0, dx, step, steps)),
+                    y.saturating_add(lerp(0, dy, step, steps)),
+                );
+                (
+                    (x.saturating_sub(TWO_FINGER_SPACING / 2), y),
+                    (x.saturating_add(TWO_FINGER_SPACING / 2), y),
+                )
+            }),
+            Gesture::Pinch {
+                center: (x, y),
+                from_distance,
+                to_distance,
+                steps,
+            } => two_finger_frames(steps, |step| {
+                let distance = lerp(from_distance, to_distance, step, steps);
+                (
+                    (x.saturating_sub(distance / 2), y),
+                    (x.saturating_add(distance / 2), y),
+                )
+            }),
+        }
+    }
+}
+
+/// Down, update and up frames of a two finger gesture
+/// with finger positions at each step from 0 to `steps`, at least 1
+fn two_finger_frames(
+    steps: u32,
+    positions: impl Fn(u32) -> ((i32, i32), (i32, i32)),
+) -> Vec<TouchFrame> {
+    let steps = steps.max(1);
+    let frame = |step, phase| {
+        let (first, second) = positions(step);
+        TouchFrame::new([
+            TouchContact::new(0, first, phase),
+            TouchContact::new(1, second, phase),
+        ])
+    };
+    let mut frames = vec![frame(0, TouchPhase::Down)];
+    frames.extend((1..=steps).map(|step| frame(step, TouchPhase::Update)));
+    frames.push(frame(steps, TouchPhase::Up));
+    frames
+}
+
+/// Value `step / steps` of the way from `from` to `to`, with zero `steps` counting as 1
+fn lerp(from: i32, to: i32, step: u32, steps: u32) -> i32 {
+    let (from, to) = (from as i64, to as i64);
+    (from + (to - from) * step as i64 / steps.max(1) as i64) as i32
+}
@@ -0,0 +1,98 @@
+//! Structured tracing of injected input, enabled by the `tracing` feature.
+
+use std::fmt;
+
+use windows::Win32::UI::Input::{KeyboardAndMouse, Pointer};
+
+use crate::dry_run;
+
+/// Enter a span for one simulated input, covering everything it injects
+pub fn simulate(input: &dyn fmt::Debug) -> tracing::span::EnteredSpan {
+    tracing::debug_span!("simulate", ?input).entered()
+}
+
+/// Emit an event with the decoded fields of an `INPUT` record
+pub fn input(input: &KeyboardAndMouse::INPUT) {
+    let description = dry_run::describe(input);
+    match input.r#type {
+        KeyboardAndMouse::INPUT_KEYBOARD => {
+            let ki = unsafe { input.Anonymous.ki };
+            tracing::trace!(
+                r#type = "keyboard",
+                vk = ki.wVk.0,
+                scan = ki.wScan,
+                flags = ki.dwFlags.0,
+                extra_info = ki.dwExtraInfo,
+                "{}",
+                description
+            );
+        }
+        KeyboardAndMouse::INPUT_MOUSE => {
+            let mi = unsafe { input.Anonymous.mi };
+            tracing::trace!(
+                r#type = "mouse",
+                dx = mi.dx,
+                dy = mi.dy,
+                mouse_data = mi.mouseData,
+                flags = mi.dwFlags.0,
+                extra_info = mi.dwExtraInfo,
+                "{}",
+                description
+            );
+        }
+        KeyboardAndMouse::INPUT_HARDWARE => {
+            let hi = unsafe { input.Anonymous.hi };
+            tracing::trace!(
+                r#type = "hardware",
+                message = hi.uMsg,
+                w_param_low = hi.wParamL,
+                w_param_high = hi.wParamH,
+                "{}",
+                description
+            );
+        }
+        _ => tracing::trace!("{}", description),
+    }
+}
+
+/// Emit an event with the result of `SendInput`
+pub fn send_input_result(requested: usize, inserted: u32) {
+    if inserted as usize == requested {
+        tracing::debug!(requested, inserted, "SendInput");
+    } else {
+        tracing::warn!(
+            requested,
+            inserted,
+            "SendInput inserted fewer events than requested, input may be blocked"
+        );
+    }
+}
+
+/// Emit an event for every contact of a touch frame
+pub fn touch(contacts: &[Pointer::POINTER_TOUCH_INFO]) {
+    for contact in contacts {
+        tracing::trace!(
+            r#type = "touch",
+            id = contact.pointerInfo.pointerId,
+            flags = contact.pointerInfo.pointerFlags.0,
+            "{}",
+            dry_run::describe_touch(contact)
+        );
+    }
+}
+
+/// Emit an event for a pen state
+pub fn pen(pen: &Pointer::POINTER_PEN_INFO) {
+    tracing::trace!(
+        r#type = "pen",
+        flags = pen.pointerInfo.pointerFlags.0,
+        pen_flags = pen.penFlags,
+        "{}",
+        dry_run::describe_pen(pen)
+    );
+}
+
+/// Emit an event when injecting pointer input failed
+pub fn injection_error(error: &windows::core::Error) {
+    tracing::warn!(%error, "pointer injection failed");
+}
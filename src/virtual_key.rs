@@ -7,6 +7,7 @@ macro_rules! virtual_key_enum {
     ) => {
         /// https://learn.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes
         #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub enum VirtualKey {
             $(
                 $(#[$attr])*
@@ -20,6 +21,13 @@ macro_rules! virtual_key_enum {
         }
 
         impl VirtualKey {
+            /// Every virtual key in declaration order
+            pub const ALL: &'static [VirtualKey] = &[
+                $(
+                    VirtualKey::$variant,
+                )*
+            ];
+
             pub fn code(&self) -> windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY {
                 match self {
                     $(
@@ -27,6 +35,31 @@ macro_rules! virtual_key_enum {
                     )*
                 }
             }
+
+            /// Name of the virtual-key code constant in the Windows API.
+            /// ```
+            /// # use kemuler_windows::VirtualKey;
+            /// assert_eq!(VirtualKey::LShift.win32_name(), "VK_LSHIFT");
+            /// ```
+            pub fn win32_name(&self) -> &'static str {
+                match self {
+                    $(
+                        VirtualKey::$variant => stringify!($og_ident),
+                    )*
+                }
+            }
+
+            /// Find the virtual key for a virtual-key code.
+            /// Some codes are shared by multiple keys (e.g. `VK_KANA` and `VK_HANGUL`),
+            /// in that case the first declared key is returned.
+            /// ```
+            /// # use kemuler_windows::VirtualKey;
+            /// assert_eq!(VirtualKey::from_code(0x41), Some(VirtualKey::A));
+            /// assert_eq!(VirtualKey::from_code(0xFFFF), None);
+            /// ```
+            pub fn from_code(code: u16) -> Option<VirtualKey> {
+                VirtualKey::ALL.iter().copied().find(|key| key.code().0 == code)
+            }
         }
     };
 }
@@ -627,3 +660,54 @@ virtual_key_enum! {
     /// Clear key
     OemClear => VK_OEM_CLEAR
 }
+
+impl VirtualKey {
+    /// Return true if the key is an extended key.
+    /// Extended keys share their scan code with another key
+    /// and are told apart by the extended-key flag, e.g. [`VirtualKey::RControl`]
+    /// and [`VirtualKey::LControl`] or [`VirtualKey::Delete`] and [`VirtualKey::Decimal`].
+    /// ```
+    /// # use kemuler_windows::VirtualKey;
+    /// assert!(VirtualKey::RightArrow.is_extended());
+    /// assert!(!VirtualKey::NumPad6.is_extended());
+    /// ```
+    pub fn is_extended(&self) -> bool {
+        matches!(
+            self,
+            VirtualKey::RControl
+                | VirtualKey::RAlt
+                | VirtualKey::Insert
+                | VirtualKey::Delete
+                | VirtualKey::Home
+                | VirtualKey::End
+                | VirtualKey::PageUp
+                | VirtualKey::PageDown
+                | VirtualKey::LeftArrow
+                | VirtualKey::UpArrow
+                | VirtualKey::RightArrow
+                | VirtualKey::DownArrow
+                | VirtualKey::NumLock
+                | VirtualKey::Divide
+                | VirtualKey::PrintScreen
+                | VirtualKey::Cancel
+                | VirtualKey::LWin
+                | VirtualKey::RWin
+                | VirtualKey::Apps
+        )
+    }
+
+    /// Find the key that types an ASCII letter or digit, ignoring case.
+    /// ```
+    /// # use kemuler_windows::VirtualKey;
+    /// assert_eq!(VirtualKey::from_alphanumeric('a'), Some(VirtualKey::A));
+    /// assert_eq!(VirtualKey::from_alphanumeric('7'), Some(VirtualKey::Num7));
+    /// assert_eq!(VirtualKey::from_alphanumeric('!'), None);
+    /// ```
+    pub fn from_alphanumeric(char: char) -> Option<VirtualKey> {
+        if char.is_ascii_alphanumeric() {
+            VirtualKey::from_code(char.to_ascii_uppercase() as u16)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,503 @@
+//! Find, focus and wait for top-level windows.
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use kemuler::simulator::Simulate;
+//! # use kemuler_windows::{VirtualKey, Windows, window::*};
+//! let notepad = WindowQuery::new().process_name("notepad.exe");
+//! let window = Window::wait_for(&notepad, Duration::from_secs(5)).unwrap();
+//! let mut windows = Windows::new();
+//! window.focus(&mut windows).unwrap();
+//! window.wait_until_foreground(Duration::from_secs(1)).unwrap();
+//! windows.simulate(VirtualKey::A.down());
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Foundation::{CloseHandle, BOOL, HWND, LPARAM, POINT, RECT},
+        Graphics::Gdi::ClientToScreen,
+        System::Threading::{self, GetCurrentThreadId},
+        UI::{HiDpi::GetDpiForWindow, WindowsAndMessaging},
+    },
+};
+
+use crate::{inner, VirtualKey, Windows};
+
+/// How often [`Window::wait_for`] and [`Window::wait_until_foreground`] check again
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How to match a window title
+#[derive(Debug, Clone)]
+pub enum TitleMatch {
+    Exact(String),
+    Contains(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl TitleMatch {
+    pub fn matches(&self, title: &str) -> bool {
+        match self {
+            TitleMatch::Exact(exact) => title == exact,
+            TitleMatch::Contains(part) => title.contains(part.as_str()),
+            #[cfg(feature = "regex")]
+            TitleMatch::Regex(regex) => regex.is_match(title),
+        }
+    }
+}
+
+/// What is known about a window when matching it against a [`WindowQuery`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct WindowInfo {
+    pub title: String,
+    pub class_name: String,
+    /// File name of the executable, e.g. `notepad.exe`.
+    /// `None` if the process cannot be queried
+    pub process_name: Option<String>,
+    pub is_visible: bool,
+}
+
+/// Criteria a window must all match.
+/// Hidden windows are not matched unless [`WindowQuery::include_hidden`] is used.
+/// ```
+/// # use kemuler_windows::window::*;
+/// let query = WindowQuery::new()
+///     .title_contains("Notepad")
+///     .process_name("NOTEPAD.EXE");
+/// let info = WindowInfo {
+///     title: "Untitled - Notepad".to_string(),
+///     class_name: "Notepad".to_string(),
+///     process_name: Some("notepad.exe".to_string()),
+///     is_visible: true,
+/// };
+/// assert!(query.matches(&info));
+/// assert!(!query.clone().class_name("Edit").matches(&info));
+/// assert!(!query.matches(&WindowInfo { is_visible: false, ..info }));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WindowQuery {
+    title: Option<TitleMatch>,
+    class_name: Option<String>,
+    process_name: Option<String>,
+    include_hidden: bool,
+}
+
+impl WindowQuery {
+    pub fn new() -> WindowQuery {
+        WindowQuery::default()
+    }
+
+    /// Match the whole title
+    pub fn title(self, title: impl Into<String>) -> WindowQuery {
+        self.title_match(TitleMatch::Exact(title.into()))
+    }
+
+    /// Match part of the title
+    pub fn title_contains(self, part: impl Into<String>) -> WindowQuery {
+        self.title_match(TitleMatch::Contains(part.into()))
+    }
+
+    /// Match the title against a regular expression
+    #[cfg(feature = "regex")]
+    pub fn title_regex(self, regex: regex::Regex) -> WindowQuery {
+        self.title_match(TitleMatch::Regex(regex))
+    }
+
+    pub fn title_match(mut self, title: TitleMatch) -> WindowQuery {
+        self.title = Some(title);
+        self
+    }
+
+    /// Match the window class name exactly
+    pub fn class_name(mut self, class_name: impl Into<String>) -> WindowQuery {
+        self.class_name = Some(class_name.into());
+        self
+    }
+
+    /// Match the file name of the process executable, ignoring case
+    pub fn process_name(mut self, process_name: impl Into<String>) -> WindowQuery {
+        self.process_name = Some(process_name.into());
+        self
+    }
+
+    pub fn include_hidden(mut self, include_hidden: bool) -> WindowQuery {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        (self.include_hidden || info.is_visible)
+            && self
+                .title
+                .as_ref()
+                .is_none_or(|title| title.matches(&info.title))
+            && self
+                .class_name
+                .as_ref()
+                .is_none_or(|class_name| *class_name == info.class_name)
+            && self.process_name.as_ref().is_none_or(|process_name| {
+                info.process_name
+                    .as_ref()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(process_name))
+            })
+    }
+
+    /// Match a window, only querying what the query checks.
+    /// Opening the process for its name is slow and fails for elevated processes.
+    fn matches_window(&self, window: &Window) -> bool {
+        let info = WindowInfo {
+            title: match self.title {
+                Some(_) => window.title(),
+                None => String::new(),
+            },
+            class_name: match self.class_name {
+                Some(_) => window.class_name(),
+                None => String::new(),
+            },
+            process_name: self
+                .process_name
+                .as_ref()
+                .and_then(|_| window.process_name()),
+            is_visible: window.is_visible(),
+        };
+        self.matches(&info)
+    }
+}
+
+/// Error returned when waiting for a window takes too long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WaitTimeout(pub Duration);
+
+impl fmt::Display for WaitTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "timed out after {:?}", self.0)
+    }
+}
+
+impl Error for WaitTimeout {}
+
+/// A top-level window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub hwnd: HWND,
+}
+
+impl Window {
+    pub fn new(hwnd: HWND) -> Window {
+        Window { hwnd }
+    }
+
+    /// The window that currently receives keyboard input from `SendInput`,
+    /// `None` if there is none, e.g. while focus is changing.
+    pub fn foreground() -> Option<Window> {
+        let hwnd = unsafe { WindowsAndMessaging::GetForegroundWindow() };
+        (hwnd.0 != 0).then_some(Window { hwnd })
+    }
+
+    /// Every top-level window
+    pub fn all() -> Vec<Window> {
+        unsafe extern "system" fn push(hwnd: HWND, windows: LPARAM) -> BOOL {
+            // SAFETY: `windows` is the `Vec` passed to `EnumWindows` below
+            let windows = &mut *(windows.0 as *mut Vec<Window>);
+            windows.push(Window { hwnd });
+            BOOL::from(true)
+        }
+        let mut windows = Vec::new();
+        unsafe {
+            WindowsAndMessaging::EnumWindows(
+                Some(push),
+                LPARAM(&mut windows as *mut Vec<Window> as isize),
+            )
+        };
+        windows
+    }
+
+    /// Every top-level window matching the query
+    pub fn find_all(query: &WindowQuery) -> Vec<Window> {
+        Window::all()
+            .into_iter()
+            .filter(|window| query.matches_window(window))
+            .collect()
+    }
+
+    /// First top-level window matching the query in z-order
+    pub fn find(query: &WindowQuery) -> Option<Window> {
+        Window::all()
+            .into_iter()
+            .find(|window| query.matches_window(window))
+    }
+
+    /// Wait until a window matching the query exists.
+    pub fn wait_for(query: &WindowQuery, timeout: Duration) -> Result<Window, WaitTimeout> {
+        poll(timeout, || Window::find(query))
+    }
+
+    pub fn title(&self) -> String {
+        let length = unsafe { WindowsAndMessaging::GetWindowTextLengthW(self.hwnd) };
+        let mut buffer = vec![0; length.max(0) as usize + 1];
+        let length = unsafe { WindowsAndMessaging::GetWindowTextW(self.hwnd, &mut buffer) };
+        String::from_utf16_lossy(&buffer[..length.max(0) as usize])
+    }
+
+    pub fn class_name(&self) -> String {
+        // class names are at most 256 characters long
+        let mut buffer = [0; 257];
+        let length = unsafe { WindowsAndMessaging::GetClassNameW(self.hwnd, &mut buffer) };
+        String::from_utf16_lossy(&buffer[..length.max(0) as usize])
+    }
+
+    pub fn process_id(&self) -> u32 {
+        let mut process_id = 0;
+        unsafe { WindowsAndMessaging::GetWindowThreadProcessId(self.hwnd, Some(&mut process_id)) };
+        process_id
+    }
+
+    /// File name of the process executable, e.g. `notepad.exe`.
+    /// Return `None` if the process cannot be queried, e.g. an elevated process.
+    pub fn process_name(&self) -> Option<String> {
+        let process = unsafe {
+            Threading::OpenProcess(
+                Threading::PROCESS_QUERY_LIMITED_INFORMATION,
+                false,
+                self.process_id(),
+            )
+        }
+        .ok()?;
+        let mut buffer = [0; 1024];
+        let mut length = buffer.len() as u32;
+        let is_ok = unsafe {
+            Threading::QueryFullProcessImageNameW(
+                process,
+                Threading::PROCESS_NAME_WIN32,
+                PWSTR(buffer.as_mut_ptr()),
+                &mut length,
+            )
+        };
+        unsafe { CloseHandle(process) };
+        if !is_ok.as_bool() {
+            return None;
+        }
+        let path = String::from_utf16_lossy(&buffer[..length as usize]);
+        Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+
+    pub fn is_visible(&self) -> bool {
+        unsafe { WindowsAndMessaging::IsWindowVisible(self.hwnd) }.as_bool()
+    }
+
+    /// Return false once the window is destroyed
+    pub fn exists(&self) -> bool {
+        unsafe { WindowsAndMessaging::IsWindow(self.hwnd) }.as_bool()
+    }
+
+    pub fn info(&self) -> WindowInfo {
+        WindowInfo {
+            title: self.title(),
+            class_name: self.class_name(),
+            process_name: self.process_name(),
+            is_visible: self.is_visible(),
+        }
+    }
+
+    pub fn is_foreground(&self) -> bool {
+        Window::foreground() == Some(*self)
+    }
+
+    /// Bring the window to the foreground, restoring it if minimized.
+    /// Return true if the window is the foreground window afterward.
+    ///
+    /// Windows only lets the process that received the last input change the foreground window.
+    /// This works around it by attaching to the input of the current foreground window's thread,
+    /// and by tapping ALT through `windows` if that is not enough,
+    /// so the tap honors its dry run, kill switch, foreground guard and signature.
+    /// Return the error if `windows` refuses to send the tap.
+    pub fn focus(&self, windows: &mut Windows) -> Result<bool, crate::Error> {
+        unsafe {
+            if WindowsAndMessaging::IsIconic(self.hwnd).as_bool() {
+                WindowsAndMessaging::ShowWindow(self.hwnd, WindowsAndMessaging::SW_RESTORE);
+            }
+            let current_thread = GetCurrentThreadId();
+            let foreground_thread = Window::foreground()
+                .map(|foreground| {
+                    WindowsAndMessaging::GetWindowThreadProcessId(foreground.hwnd, None)
+                })
+                .unwrap_or(current_thread);
+            let attach = foreground_thread != current_thread;
+            if attach {
+                Threading::AttachThreadInput(current_thread, foreground_thread, true);
+            }
+            WindowsAndMessaging::BringWindowToTop(self.hwnd);
+            WindowsAndMessaging::SetForegroundWindow(self.hwnd);
+            if attach {
+                Threading::AttachThreadInput(current_thread, foreground_thread, false);
+            }
+            if !self.is_foreground() {
+                // a keystroke makes this process the one that received the last input
+                let mut alt_tap = inner::send_input::virtual_key_down(VirtualKey::Alt);
+                alt_tap.extend(inner::send_input::virtual_key_up(VirtualKey::Alt));
+                windows.send(&alt_tap)?;
+                WindowsAndMessaging::SetForegroundWindow(self.hwnd);
+            }
+        }
+        Ok(self.is_foreground())
+    }
+
+    /// Screen position of the top-left corner of the client area.
+    /// Return `None` if the window does not exist.
+    pub fn client_origin(&self) -> Option<(i32, i32)> {
+        let mut point = POINT { x: 0, y: 0 };
+        unsafe { ClientToScreen(self.hwnd, &mut point) }
+            .as_bool()
+            .then_some((point.x, point.y))
+    }
+
+    /// Screen position of the top-left corner of the window frame.
+    /// Return `None` if the window does not exist.
+    pub fn frame_origin(&self) -> Option<(i32, i32)> {
+        let mut rect = RECT::default();
+        unsafe { WindowsAndMessaging::GetWindowRect(self.hwnd, &mut rect) }
+            .as_bool()
+            .then_some((rect.left, rect.top))
+    }
+
+    /// Dots per inch of the monitor the window is on, 96 is 100% scaling.
+    /// Return `None` if the window does not exist.
+    pub fn dpi(&self) -> Option<u32> {
+        let dpi = unsafe { GetDpiForWindow(self.hwnd) };
+        (dpi != 0).then_some(dpi)
+    }
+
+    /// Wait until the window is the foreground window.
+    pub fn wait_until_foreground(&self, timeout: Duration) -> Result<(), WaitTimeout> {
+        poll(timeout, || self.is_foreground().then_some(()))
+    }
+}
+
+/// Part of a window that [`WindowMousePosition`] is relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WindowArea {
+    /// The client area, excluding the title bar and borders
+    #[default]
+    Client,
+    /// The whole window including the title bar and borders
+    Frame,
+}
+
+/// Mouse position relative to a window, resolved when simulated.
+///
+/// Simulating `SetTo<WindowMousePosition, (i32, i32)>` on [`Windows`](crate::Windows)
+/// moves the mouse to an offset from the top-left corner of the window's area,
+/// wherever the window is when the event is simulated.
+/// ```no_run
+/// # use kemuler::simulator::Simulate;
+/// # use kemuler_windows::{Windows, window::*};
+/// let window = Window::foreground().unwrap();
+/// Windows::new().simulate(WindowMousePosition::client(window).move_to(120, 40));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowMousePosition {
+    pub window: Window,
+    pub area: WindowArea,
+    /// Offsets are in 96 DPI units and are scaled by the window's DPI,
+    /// so the same offset hits the same spot at any display scaling.
+    pub is_dpi_scaled: bool,
+}
+
+impl WindowMousePosition {
+    /// Relative to the client area, offsets in physical pixels
+    pub fn client(window: Window) -> WindowMousePosition {
+        WindowMousePosition {
+            window,
+            area: WindowArea::Client,
+            is_dpi_scaled: false,
+        }
+    }
+
+    /// Relative to the window frame, offsets in physical pixels
+    pub fn frame(window: Window) -> WindowMousePosition {
+        WindowMousePosition {
+            area: WindowArea::Frame,
+            ..WindowMousePosition::client(window)
+        }
+    }
+
+    /// Scale offsets by the window's DPI, see [`WindowMousePosition::is_dpi_scaled`]
+    pub fn dpi_scaled(mut self) -> WindowMousePosition {
+        self.is_dpi_scaled = true;
+        self
+    }
+
+    /// Move the mouse to `(x, y)` relative to the window.
+    /// This is a convenience shorthand for
+    /// ```
+    /// # use kemuler::input_event::*;
+    /// # let this = 0i32;
+    /// # let (x, y) = (0, 0);
+    /// SetTo { input: this, to: (x, y) }
+    /// # ;
+    /// ```
+    pub fn move_to(self, x: i32, y: i32) -> kemuler::input_event::SetTo<Self, (i32, i32)> {
+        kemuler::input_event::SetTo::new(self, (x, y))
+    }
+
+    /// Screen position of `offset` given the origin of the window's area and the window's DPI.
+    /// ```
+    /// # use kemuler_windows::window::*;
+    /// # use windows::Win32::Foundation::HWND;
+    /// let position = WindowMousePosition::client(Window::new(HWND(1)));
+    /// // window on a monitor left of the primary monitor
+    /// assert_eq!(position.resolve_with((-1900, 100), 144, (10, 20)), (-1890, 120));
+    /// // 150% scaling
+    /// assert_eq!(position.dpi_scaled().resolve_with((-1900, 100), 144, (10, 20)), (-1885, 130));
+    /// ```
+    pub fn resolve_with(&self, origin: (i32, i32), dpi: u32, offset: (i32, i32)) -> (i32, i32) {
+        let scale = |value: i32| {
+            if self.is_dpi_scaled {
+                (value as i64 * dpi as i64 / 96) as i32
+            } else {
+                value
+            }
+        };
+        (origin.0 + scale(offset.0), origin.1 + scale(offset.1))
+    }
+
+    /// Screen position of `offset` where the window is now.
+    /// Return `None` if the window does not exist.
+    pub fn resolve(&self, offset: (i32, i32)) -> Option<(i32, i32)> {
+        let origin = match self.area {
+            WindowArea::Client => self.window.client_origin()?,
+            WindowArea::Frame => self.window.frame_origin()?,
+        };
+        let dpi = if self.is_dpi_scaled {
+            self.window.dpi()?
+        } else {
+            96
+        };
+        Some(self.resolve_with(origin, dpi, offset))
+    }
+}
+
+/// Call `f` until it returns `Some` or `timeout` elapsed
+fn poll<T>(timeout: Duration, mut f: impl FnMut() -> Option<T>) -> Result<T, WaitTimeout> {
+    let start = Instant::now();
+    loop {
+        if let Some(value) = f() {
+            return Ok(value);
+        }
+        if start.elapsed() >= timeout {
+            return Err(WaitTimeout(timeout));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,393 @@
+//! Send input to a specific window with window messages.
+//!
+//! `SendInput` only reaches the foreground window.
+//! [`WindowTarget`] instead posts (or sends) `WM_KEYDOWN`, `WM_CHAR`, `WM_LBUTTONDOWN`, ... messages
+//! straight to a window, so a background window can be automated while the user keeps working.
+//! Applications that read the keyboard or mouse state directly, e.g. most games,
+//! will not see this input.
+//!
+//! The packing of `wParam` and `lParam` is done by [`KeyLParam`], [`point_lparam`] and [`make_wparam`]
+//! which do not call into Windows.
+
+use kemuler::{
+    common_inputs,
+    input_event::{ChangeBy, SetTo},
+    simulator::Simulate,
+};
+use windows::Win32::{
+    Foundation::{self, HWND, LPARAM, POINT, WPARAM},
+    Graphics::Gdi::ClientToScreen,
+    System::SystemServices,
+    UI::WindowsAndMessaging,
+};
+
+use crate::{
+    inner, windowsify_common_key, windowsify_common_mouse_button, Error, MouseButton, TrySimulate,
+    VirtualKey,
+};
+
+/// `lParam` of keystroke messages such as `WM_KEYDOWN`, `WM_KEYUP` and `WM_CHAR`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct KeyLParam {
+    /// Number of times the keystroke is autorepeated
+    pub repeat_count: u16,
+    pub scan_code: u8,
+    /// The key is an extended key, see [`VirtualKey::is_extended`]
+    pub is_extended: bool,
+    /// The ALT key is held down, only set for `WM_SYSKEYDOWN` and `WM_SYSKEYUP`
+    pub context_code: bool,
+    /// The key was down before the message is sent
+    pub previous_state: bool,
+    /// The key is being released
+    pub transition_state: bool,
+}
+
+impl KeyLParam {
+    /// `lParam` of a key being pressed once
+    pub fn key_down(scan_code: u8, is_extended: bool) -> KeyLParam {
+        KeyLParam {
+            repeat_count: 1,
+            scan_code,
+            is_extended,
+            ..Default::default()
+        }
+    }
+
+    /// `lParam` of a key being released
+    pub fn key_up(scan_code: u8, is_extended: bool) -> KeyLParam {
+        KeyLParam {
+            previous_state: true,
+            transition_state: true,
+            ..KeyLParam::key_down(scan_code, is_extended)
+        }
+    }
+
+    /// ```
+    /// # use kemuler_windows::window_target::KeyLParam;
+    /// // left shift
+    /// assert_eq!(KeyLParam::key_down(0x2A, false).pack().0, 0x002A_0001);
+    /// assert_eq!(KeyLParam::key_up(0x2A, false).pack().0, 0xC02A_0001);
+    /// // right arrow
+    /// assert_eq!(KeyLParam::key_down(0x4D, true).pack().0, 0x014D_0001);
+    /// let sys_key_down = KeyLParam {
+    ///     context_code: true,
+    ///     ..KeyLParam::key_down(0x3E, false)
+    /// };
+    /// assert_eq!(sys_key_down.pack().0, 0x203E_0001);
+    /// ```
+    pub fn pack(&self) -> LPARAM {
+        let bits = self.repeat_count as u32
+            | (self.scan_code as u32) << 16
+            | (self.is_extended as u32) << 24
+            | (self.context_code as u32) << 29
+            | (self.previous_state as u32) << 30
+            | (self.transition_state as u32) << 31;
+        LPARAM(bits as isize)
+    }
+}
+
+/// `lParam` of mouse messages, `x` in the low-order word and `y` in the high-order word
+/// ```
+/// # use kemuler_windows::window_target::point_lparam;
+/// assert_eq!(point_lparam(10, 20).0, 0x0014_000A);
+/// // coordinates are signed, e.g. on a monitor left of the primary monitor
+/// assert_eq!(point_lparam(-1, 0).0, 0x0000_FFFF);
+/// ```
+pub fn point_lparam(x: i32, y: i32) -> LPARAM {
+    LPARAM(((y as u16 as u32) << 16 | x as u16 as u32) as isize)
+}
+
+/// `wParam` made of two words, like the `MAKEWPARAM` macro.
+/// Mouse messages use the low-order word for the `MK_*` key state,
+/// and the high-order word for the wheel delta or the X button.
+/// ```
+/// # use kemuler_windows::window_target::make_wparam;
+/// // `WM_MOUSEWHEEL` scrolling one notch toward the user with `MK_CONTROL`
+/// assert_eq!(make_wparam(0x0008, -120i16 as u16).0, 0xFF88_0008);
+/// ```
+pub fn make_wparam(low: u16, high: u16) -> WPARAM {
+    WPARAM(((high as u32) << 16 | low as u32) as usize)
+}
+
+/// How messages are delivered to the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Delivery {
+    /// `PostMessageW`, return immediately
+    #[default]
+    Post,
+    /// `SendMessageW`, wait for the window to process the message
+    Send,
+}
+
+/// Simulator that sends window messages to a specific window.
+///
+/// Mouse positions are in client-area coordinates of the window.
+/// The state of modifier keys and mouse buttons is tracked from the simulated events only
+/// and reported in the `wParam` of mouse messages.
+/// Wheel deltas are clamped to the `i16` range of the `wParam`, about 273 notches.
+/// A message that cannot be delivered, e.g. because the window is gone,
+/// is reported as [`Error::Injection`].
+#[derive(Debug, Clone)]
+pub struct WindowTarget {
+    hwnd: HWND,
+    delivery: Delivery,
+    position: (i32, i32),
+    key_state: SystemServices::MODIFIERKEYS_FLAGS,
+    is_alt_down: bool,
+}
+
+impl WindowTarget {
+    pub fn new(hwnd: HWND) -> WindowTarget {
+        WindowTarget {
+            hwnd,
+            delivery: Delivery::default(),
+            position: (0, 0),
+            key_state: SystemServices::MODIFIERKEYS_FLAGS(0),
+            is_alt_down: false,
+        }
+    }
+
+    pub fn with_delivery(mut self, delivery: Delivery) -> WindowTarget {
+        self.delivery = delivery;
+        self
+    }
+
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+
+    fn deliver(&self, message: u32, wparam: WPARAM, lparam: LPARAM) -> Result<(), Error> {
+        match self.delivery {
+            Delivery::Post => unsafe {
+                WindowsAndMessaging::PostMessageW(self.hwnd, message, wparam, lparam)
+                    .ok()
+                    .map_err(Error::Injection)
+            },
+            // the result of `SendMessageW` belongs to the message, only the last error tells failure
+            Delivery::Send => unsafe {
+                Foundation::SetLastError(Foundation::ERROR_SUCCESS);
+                WindowsAndMessaging::SendMessageW(self.hwnd, message, wparam, lparam);
+                match Foundation::GetLastError() {
+                    Foundation::ERROR_SUCCESS => Ok(()),
+                    error => Err(Error::Injection(error.to_hresult().into())),
+                }
+            },
+        }
+    }
+
+    fn set_key_state(&mut self, flag: SystemServices::MODIFIERKEYS_FLAGS, is_down: bool) {
+        if is_down {
+            self.key_state.0 |= flag.0;
+        } else {
+            self.key_state.0 &= !flag.0;
+        }
+    }
+
+    fn key(&mut self, key: VirtualKey, is_down: bool) -> Result<(), Error> {
+        let was_alt_down = self.is_alt_down;
+        match key {
+            VirtualKey::Shift | VirtualKey::LShift | VirtualKey::RShift => {
+                self.set_key_state(SystemServices::MK_SHIFT, is_down)
+            }
+            VirtualKey::Control | VirtualKey::LControl | VirtualKey::RControl => {
+                self.set_key_state(SystemServices::MK_CONTROL, is_down)
+            }
+            VirtualKey::Alt | VirtualKey::LAlt | VirtualKey::RAlt => self.is_alt_down = is_down,
+            _ => {}
+        }
+        // keys pressed while ALT is held, and ALT itself, are system keys unless CTRL is held
+        let is_control_down = self.key_state.0 & SystemServices::MK_CONTROL.0 != 0;
+        let is_system = (was_alt_down || self.is_alt_down) && !is_control_down;
+        let scan_code = inner::query::scan_code(key) as u8;
+        let lparam = if is_down {
+            KeyLParam::key_down(scan_code, key.is_extended())
+        } else {
+            KeyLParam::key_up(scan_code, key.is_extended())
+        };
+        let lparam = KeyLParam {
+            context_code: is_system && self.is_alt_down,
+            ..lparam
+        };
+        let message = match (is_system, is_down) {
+            (false, true) => WindowsAndMessaging::WM_KEYDOWN,
+            (false, false) => WindowsAndMessaging::WM_KEYUP,
+            (true, true) => WindowsAndMessaging::WM_SYSKEYDOWN,
+            (true, false) => WindowsAndMessaging::WM_SYSKEYUP,
+        };
+        self.deliver(message, WPARAM(key.code().0 as usize), lparam.pack())
+    }
+
+    fn char(&mut self, char: char, is_down: bool) -> Result<(), Error> {
+        // `WM_CHAR` is only sent once per keystroke
+        if !is_down {
+            return Ok(());
+        }
+        // from the doc: A buffer of length 2 is large enough to encode any `char`.
+        let mut utf16_bytes = [0; 2];
+        for c in char.encode_utf16(&mut utf16_bytes) {
+            self.deliver(
+                WindowsAndMessaging::WM_CHAR,
+                WPARAM(*c as usize),
+                KeyLParam::key_down(0, false).pack(),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn mouse_button(&mut self, button: MouseButton, is_down: bool) -> Result<(), Error> {
+        let (flag, x_button) = match button {
+            MouseButton::Left => (SystemServices::MK_LBUTTON, 0),
+            MouseButton::Middle => (SystemServices::MK_MBUTTON, 0),
+            MouseButton::Right => (SystemServices::MK_RBUTTON, 0),
+            MouseButton::X1 => (SystemServices::MK_XBUTTON1, WindowsAndMessaging::XBUTTON1),
+            MouseButton::X2 => (SystemServices::MK_XBUTTON2, WindowsAndMessaging::XBUTTON2),
+        };
+        let message = match (button, is_down) {
+            (MouseButton::Left, true) => WindowsAndMessaging::WM_LBUTTONDOWN,
+            (MouseButton::Left, false) => WindowsAndMessaging::WM_LBUTTONUP,
+            (MouseButton::Middle, true) => WindowsAndMessaging::WM_MBUTTONDOWN,
+            (MouseButton::Middle, false) => WindowsAndMessaging::WM_MBUTTONUP,
+            (MouseButton::Right, true) => WindowsAndMessaging::WM_RBUTTONDOWN,
+            (MouseButton::Right, false) => WindowsAndMessaging::WM_RBUTTONUP,
+            (MouseButton::X1 | MouseButton::X2, true) => WindowsAndMessaging::WM_XBUTTONDOWN,
+            (MouseButton::X1 | MouseButton::X2, false) => WindowsAndMessaging::WM_XBUTTONUP,
+        };
+        self.set_key_state(flag, is_down);
+        self.deliver(
+            message,
+            make_wparam(self.key_state.0 as u16, x_button),
+            point_lparam(self.position.0, self.position.1),
+        )
+    }
+
+    fn mouse_move_to(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        self.position = (x, y);
+        self.deliver(
+            WindowsAndMessaging::WM_MOUSEMOVE,
+            make_wparam(self.key_state.0 as u16, 0),
+            point_lparam(x, y),
+        )
+    }
+
+    fn mouse_scroll(&mut self, x: i32, y: i32) -> Result<(), Error> {
+        // unlike other mouse messages, wheel messages use screen coordinates
+        let mut point = POINT {
+            x: self.position.0,
+            y: self.position.1,
+        };
+        unsafe { ClientToScreen(self.hwnd, &mut point) };
+        let lparam = point_lparam(point.x, point.y);
+        for (message, delta) in [
+            (WindowsAndMessaging::WM_MOUSEWHEEL, y),
+            (WindowsAndMessaging::WM_MOUSEHWHEEL, x),
+        ] {
+            if delta != 0 {
+                let delta = delta.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+                let wparam = make_wparam(self.key_state.0 as u16, delta as u16);
+                self.deliver(message, wparam, lparam)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TrySimulate<SetTo<VirtualKey, bool>> for WindowTarget {
+    fn try_simulate(&mut self, simulatable: SetTo<VirtualKey, bool>) -> Result<(), Error> {
+        let SetTo {
+            input: key,
+            to: is_down,
+        } = simulatable;
+        self.key(key, is_down)
+    }
+}
+
+impl TrySimulate<SetTo<common_inputs::Key, bool>> for WindowTarget {
+    fn try_simulate(&mut self, simulatable: SetTo<common_inputs::Key, bool>) -> Result<(), Error> {
+        let SetTo {
+            input: key,
+            to: is_down,
+        } = simulatable;
+        self.key(windowsify_common_key(key), is_down)
+    }
+}
+
+impl TrySimulate<SetTo<common_inputs::Char, bool>> for WindowTarget {
+    fn try_simulate(&mut self, simulatable: SetTo<common_inputs::Char, bool>) -> Result<(), Error> {
+        let SetTo {
+            input: char,
+            to: is_down,
+        } = simulatable;
+        self.char(char.0, is_down)
+    }
+}
+
+impl TrySimulate<SetTo<MouseButton, bool>> for WindowTarget {
+    fn try_simulate(&mut self, simulatable: SetTo<MouseButton, bool>) -> Result<(), Error> {
+        let SetTo {
+            input: button,
+            to: is_down,
+        } = simulatable;
+        self.mouse_button(button, is_down)
+    }
+}
+
+impl TrySimulate<SetTo<common_inputs::MouseButton, bool>> for WindowTarget {
+    fn try_simulate(
+        &mut self,
+        simulatable: SetTo<common_inputs::MouseButton, bool>,
+    ) -> Result<(), Error> {
+        let SetTo {
+            input: button,
+            to: is_down,
+        } = simulatable;
+        self.mouse_button(windowsify_common_mouse_button(button), is_down)
+    }
+}
+
+impl TrySimulate<SetTo<common_inputs::MousePosition, (i32, i32)>> for WindowTarget {
+    fn try_simulate(
+        &mut self,
+        simulatable: SetTo<common_inputs::MousePosition, (i32, i32)>,
+    ) -> Result<(), Error> {
+        let SetTo {
+            input: _,
+            to: position,
+        } = simulatable;
+        self.mouse_move_to(position.0, position.1)
+    }
+}
+
+impl TrySimulate<ChangeBy<common_inputs::MousePosition, (i32, i32)>> for WindowTarget {
+    fn try_simulate(
+        &mut self,
+        simulatable: ChangeBy<common_inputs::MousePosition, (i32, i32)>,
+    ) -> Result<(), Error> {
+        let ChangeBy { input: _, by } = simulatable;
+        self.mouse_move_to(
+            self.position.0.saturating_add(by.0),
+            self.position.1.saturating_add(by.1),
+        )
+    }
+}
+
+impl TrySimulate<ChangeBy<common_inputs::MouseScroll, (i32, i32)>> for WindowTarget {
+    fn try_simulate(
+        &mut self,
+        simulatable: ChangeBy<common_inputs::MouseScroll, (i32, i32)>,
+    ) -> Result<(), Error> {
+        let ChangeBy { input: _, by } = simulatable;
+        self.mouse_scroll(by.0, by.1)
+    }
+}
+
+crate::simulate_by_try_simulate! {
+    WindowTarget;
+    SetTo<VirtualKey, bool>,
+    SetTo<common_inputs::Key, bool>,
+    SetTo<common_inputs::Char, bool>,
+    SetTo<MouseButton, bool>,
+    SetTo<common_inputs::MouseButton, bool>,
+    SetTo<common_inputs::MousePosition, (i32, i32)>,
+    ChangeBy<common_inputs::MousePosition, (i32, i32)>,
+    ChangeBy<common_inputs::MouseScroll, (i32, i32)>,
+}
@@ -0,0 +1,498 @@
+//! xdotool command syntax.
+//!
+//! Each line is one command with shell-like quoting, the leading `xdotool` is optional.
+//! Commands are translated into a [`Script`] and keys are X keysym names.
+//!
+//! Supported commands are `key`, `keydown`, `keyup`, `type`, `mousemove`, `click`,
+//! `mousedown`, `mouseup` and `sleep`.
+//! `--delay` of `key` and `type` is honored, other options like `--clearmodifiers`,
+//! `--window` and `--sync` are accepted and ignored.
+//! Scroll buttons 4 to 7 of `click` scroll by one notch.
+//! `--repeat` of `click` is bounded like [`Command::click`](crate::script::Command::click)
+//! and [`Command::scroll`](crate::script::Command::scroll).
+//! ```
+//! # use kemuler_windows::{VirtualKey, input_macro::MacroEvent, xdotool};
+//! let script = xdotool::parse("xdotool key ctrl+alt+t\nxdotool type 'hello world'").unwrap();
+//! let events = script.to_macro().events;
+//! assert_eq!(events.len(), 6 + 22);
+//! assert_eq!(events[0].event, MacroEvent::Key { key: VirtualKey::Control, is_down: true });
+//! ```
+
+use std::time::Duration;
+
+use crate::{
+    script::{Command, ParseError, ParseErrorKind, Script, Statement},
+    MouseButton, VirtualKey,
+};
+
+/// X keysym names and the keys they press
+pub const KEYSYMS: &[(&str, VirtualKey)] = &[
+    ("Return", VirtualKey::Enter),
+    ("BackSpace", VirtualKey::Backspace),
+    ("Tab", VirtualKey::Tab),
+    ("ISO_Left_Tab", VirtualKey::Tab),
+    ("Escape", VirtualKey::Escape),
+    ("space", VirtualKey::Space),
+    ("Delete", VirtualKey::Delete),
+    ("Insert", VirtualKey::Insert),
+    ("Home", VirtualKey::Home),
+    ("End", VirtualKey::End),
+    ("Prior", VirtualKey::PageUp),
+    ("Page_Up", VirtualKey::PageUp),
+    ("Next", VirtualKey::PageDown),
+    ("Page_Down", VirtualKey::PageDown),
+    ("Left", VirtualKey::LeftArrow),
+    ("Up", VirtualKey::UpArrow),
+    ("Right", VirtualKey::RightArrow),
+    ("Down", VirtualKey::DownArrow),
+    ("Shift_L", VirtualKey::LShift),
+    ("Shift_R", VirtualKey::RShift),
+    ("Control_L", VirtualKey::LControl),
+    ("Control_R", VirtualKey::RControl),
+    ("Alt_L", VirtualKey::LAlt),
+    ("Alt_R", VirtualKey::RAlt),
+    ("ISO_Level3_Shift", VirtualKey::RAlt),
+    ("Meta_L", VirtualKey::LAlt),
+    ("Meta_R", VirtualKey::RAlt),
+    ("Super_L", VirtualKey::LWin),
+    ("Super_R", VirtualKey::RWin),
+    ("Menu", VirtualKey::Apps),
+    ("Caps_Lock", VirtualKey::CapsLock),
+    ("Num_Lock", VirtualKey::NumLock),
+    ("Scroll_Lock", VirtualKey::ScrollLock),
+    ("Print", VirtualKey::PrintScreen),
+    ("Pause", VirtualKey::Pause),
+    ("Break", VirtualKey::Cancel),
+    ("Help", VirtualKey::Help),
+    ("Clear", VirtualKey::Clear),
+    ("F1", VirtualKey::F1),
+    ("F2", VirtualKey::F2),
+    ("F3", VirtualKey::F3),
+    ("F4", VirtualKey::F4),
+    ("F5", VirtualKey::F5),
+    ("F6", VirtualKey::F6),
+    ("F7", VirtualKey::F7),
+    ("F8", VirtualKey::F8),
+    ("F9", VirtualKey::F9),
+    ("F10", VirtualKey::F10),
+    ("F11", VirtualKey::F11),
+    ("F12", VirtualKey::F12),
+    ("F13", VirtualKey::F13),
+    ("F14", VirtualKey::F14),
+    ("F15", VirtualKey::F15),
+    ("F16", VirtualKey::F16),
+    ("F17", VirtualKey::F17),
+    ("F18", VirtualKey::F18),
+    ("F19", VirtualKey::F19),
+    ("F20", VirtualKey::F20),
+    ("F21", VirtualKey::F21),
+    ("F22", VirtualKey::F22),
+    ("F23", VirtualKey::F23),
+    ("F24", VirtualKey::F24),
+    ("KP_0", VirtualKey::NumPad0),
+    ("KP_1", VirtualKey::NumPad1),
+    ("KP_2", VirtualKey::NumPad2),
+    ("KP_3", VirtualKey::NumPad3),
+    ("KP_4", VirtualKey::NumPad4),
+    ("KP_5", VirtualKey::NumPad5),
+    ("KP_6", VirtualKey::NumPad6),
+    ("KP_7", VirtualKey::NumPad7),
+    ("KP_8", VirtualKey::NumPad8),
+    ("KP_9", VirtualKey::NumPad9),
+    ("KP_Enter", VirtualKey::Enter),
+    ("KP_Add", VirtualKey::Add),
+    ("KP_Subtract", VirtualKey::Subtract),
+    ("KP_Multiply", VirtualKey::Multiply),
+    ("KP_Divide", VirtualKey::Divide),
+    ("KP_Decimal", VirtualKey::Decimal),
+    ("KP_Separator", VirtualKey::Separator),
+    ("KP_Home", VirtualKey::Home),
+    ("KP_End", VirtualKey::End),
+    ("KP_Prior", VirtualKey::PageUp),
+    ("KP_Next", VirtualKey::PageDown),
+    ("KP_Left", VirtualKey::LeftArrow),
+    ("KP_Up", VirtualKey::UpArrow),
+    ("KP_Right", VirtualKey::RightArrow),
+    ("KP_Down", VirtualKey::DownArrow),
+    ("KP_Insert", VirtualKey::Insert),
+    ("KP_Delete", VirtualKey::Delete),
+    ("KP_Begin", VirtualKey::Clear),
+    ("minus", VirtualKey::OemMinus),
+    ("equal", VirtualKey::OemPlus),
+    ("comma", VirtualKey::OemComma),
+    ("period", VirtualKey::OemPeriod),
+    ("semicolon", VirtualKey::Oem1),
+    ("slash", VirtualKey::Oem2),
+    ("grave", VirtualKey::Oem3),
+    ("bracketleft", VirtualKey::Oem4),
+    ("backslash", VirtualKey::Oem5),
+    ("bracketright", VirtualKey::Oem6),
+    ("apostrophe", VirtualKey::Oem7),
+    ("less", VirtualKey::Oem102),
+    ("XF86AudioMute", VirtualKey::VolumeMute),
+    ("XF86AudioLowerVolume", VirtualKey::VolumeDown),
+    ("XF86AudioRaiseVolume", VirtualKey::VolumeUp),
+    ("XF86AudioNext", VirtualKey::MediaNextTrack),
+    ("XF86AudioPrev", VirtualKey::MediaPrevTrack),
+    ("XF86AudioStop", VirtualKey::MediaStop),
+    ("XF86AudioPlay", VirtualKey::MediaPlayPause),
+    ("XF86AudioMedia", VirtualKey::LaunchMediaSelect),
+    ("XF86Mail", VirtualKey::LaunchMail),
+    ("XF86Back", VirtualKey::BrowserBack),
+    ("XF86Forward", VirtualKey::BrowserForward),
+    ("XF86Refresh", VirtualKey::BrowserRefresh),
+    ("XF86Stop", VirtualKey::BrowserStop),
+    ("XF86Search", VirtualKey::BrowserSearch),
+    ("XF86Favorites", VirtualKey::BrowserFavorites),
+    ("XF86HomePage", VirtualKey::BrowserHome),
+    ("XF86Sleep", VirtualKey::Sleep),
+    // Aliases xdotool accepts for modifiers
+    ("ctrl", VirtualKey::Control),
+    ("alt", VirtualKey::Alt),
+    ("shift", VirtualKey::Shift),
+    ("super", VirtualKey::LWin),
+    ("meta", VirtualKey::LAlt),
+];
+
+/// Find the key for an X keysym name.
+/// Besides [`KEYSYMS`], letters and digits are accepted.
+/// Names are matched exactly first and then ignoring case.
+/// ```
+/// # use kemuler_windows::{VirtualKey, xdotool::key_from_keysym};
+/// assert_eq!(key_from_keysym("Return"), Some(VirtualKey::Enter));
+/// assert_eq!(key_from_keysym("Super_L"), Some(VirtualKey::LWin));
+/// assert_eq!(key_from_keysym("KP_Enter"), Some(VirtualKey::Enter));
+/// assert_eq!(key_from_keysym("T"), Some(VirtualKey::T));
+/// assert_eq!(key_from_keysym("Hyper_Q"), None);
+/// ```
+pub fn key_from_keysym(name: &str) -> Option<VirtualKey> {
+    let mut chars = name.chars();
+    if let (Some(char), None) = (chars.next(), chars.next()) {
+        if let Some(key) = VirtualKey::from_alphanumeric(char) {
+            return Some(key);
+        }
+    }
+    KEYSYMS
+        .iter()
+        .find(|(keysym, _)| *keysym == name)
+        .or_else(|| {
+            KEYSYMS
+                .iter()
+                .find(|(keysym, _)| keysym.eq_ignore_ascii_case(name))
+        })
+        .map(|(_, key)| *key)
+}
+
+/// Parse xdotool commands into a script.
+/// ```
+/// # use std::time::Duration;
+/// # use kemuler_windows::{MouseButton, VirtualKey, script::{Command, ParseErrorKind}, xdotool};
+/// let script = xdotool::parse("mousemove 10 20\nclick --repeat 2 3\nclick 5\nsleep 0.5").unwrap();
+/// let commands = script.statements.into_iter().map(|s| s.command).collect::<Vec<_>>();
+/// assert_eq!(commands, [
+///     Command::Move(10, 20),
+///     Command::Click { button: MouseButton::Right, count: 2 },
+///     Command::Scroll { vertical: -1, horizontal: 0 },
+///     Command::Wait(Duration::from_millis(500)),
+/// ]);
+///
+/// let error = xdotool::parse("key ctrl+Retrun").unwrap_err();
+/// assert_eq!((error.column, error.kind), (10, ParseErrorKind::UnknownKey("Retrun".to_string())));
+///
+/// let error = xdotool::parse("click --repeat 4294967295 5").unwrap_err();
+/// assert_eq!((error.column, error.kind), (16, ParseErrorKind::InvalidNumber("4294967295".to_string())));
+///
+/// let error = xdotool::parse("click --repeat 4294967295 1").unwrap_err();
+/// assert_eq!((error.column, error.kind), (16, ParseErrorKind::InvalidNumber("4294967295".to_string())));
+/// ```
+pub fn parse(source: &str) -> Result<Script, ParseError> {
+    let mut statements = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        parse_line(index + 1, line, &mut statements)?;
+    }
+    Ok(Script { statements })
+}
+
+/// A shell word on a line
+struct Word {
+    text: String,
+    /// Column in characters starting at 1
+    column: usize,
+}
+
+/// Split a line into shell words, stopping at a comment
+fn split_words(line_number: usize, line: &str) -> Result<Vec<Word>, ParseError> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().enumerate().peekable();
+    while let Some(&(index, char)) = chars.peek() {
+        if char.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if char == '#' {
+            break;
+        }
+        let column = index + 1;
+        let mut text = String::new();
+        while let Some(&(quote_index, char)) = chars.peek() {
+            if char.is_whitespace() {
+                break;
+            }
+            chars.next();
+            match char {
+                '\'' | '"' => loop {
+                    match chars.next() {
+                        Some((_, quoted)) if quoted == char => break,
+                        Some((_, '\\')) if char == '"' => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => break,
+                        },
+                        Some((_, quoted)) => text.push(quoted),
+                        None => {
+                            return Err(ParseError {
+                                line: line_number,
+                                column: quote_index + 1,
+                                kind: ParseErrorKind::UnterminatedString,
+                            })
+                        }
+                    }
+                },
+                '\\' => {
+                    if let Some((_, escaped)) = chars.next() {
+                        text.push(escaped);
+                    }
+                }
+                char => text.push(char),
+            }
+        }
+        words.push(Word { text, column });
+    }
+    Ok(words)
+}
+
+/// Parse one line into statements
+fn parse_line(
+    line_number: usize,
+    line: &str,
+    statements: &mut Vec<Statement>,
+) -> Result<(), ParseError> {
+    let mut words = split_words(line_number, line)?;
+    if words.first().is_some_and(|word| word.text == "xdotool") {
+        words.remove(0);
+    }
+    let Some((name, arguments)) = words.split_first() else {
+        return Ok(());
+    };
+    let error = |column, kind| ParseError {
+        line: line_number,
+        column,
+        kind,
+    };
+    let end_column = line.chars().count() + 1;
+    let missing = |expected| error(end_column, ParseErrorKind::MissingArgument(expected));
+    let mut push = |command| {
+        statements.push(Statement {
+            line: line_number,
+            command,
+        })
+    };
+
+    let (options, arguments) = split_options(line_number, arguments)?;
+    let delay = options.delay;
+    match name.text.as_str() {
+        "key" | "keydown" | "keyup" => {
+            if arguments.is_empty() {
+                return Err(missing("keysym"));
+            }
+            for (index, word) in arguments.iter().enumerate() {
+                let mut column = word.column;
+                let mut chord = Vec::new();
+                for keysym in word.text.split('+') {
+                    let key = key_from_keysym(keysym).ok_or_else(|| {
+                        error(column, ParseErrorKind::UnknownKey(keysym.to_string()))
+                    })?;
+                    chord.push(key);
+                    column += keysym.chars().count() + 1;
+                }
+                if index > 0 && !delay.is_zero() {
+                    push(Command::Wait(delay));
+                }
+                match name.text.as_str() {
+                    "key" => push(Command::Key(vec![chord])),
+                    "keydown" => chord
+                        .into_iter()
+                        .for_each(|key| push(Command::KeyDown(key))),
+                    _ => chord
+                        .into_iter()
+                        .rev()
+                        .for_each(|key| push(Command::KeyUp(key))),
+                }
+            }
+        }
+        "type" => {
+            if arguments.is_empty() {
+                return Err(missing("text"));
+            }
+            let text = arguments
+                .iter()
+                .map(|word| word.text.as_str())
+                .collect::<String>();
+            if delay.is_zero() {
+                push(Command::Type(text));
+            } else {
+                for (index, char) in text.chars().enumerate() {
+                    if index > 0 {
+                        push(Command::Wait(delay));
+                    }
+                    push(Command::Type(char.to_string()));
+                }
+            }
+        }
+        "mousemove" => {
+            let [x, y] = arguments else {
+                return match arguments.get(2) {
+                    Some(word) => Err(error(
+                        word.column,
+                        ParseErrorKind::UnexpectedArgument(word.text.clone()),
+                    )),
+                    None => Err(missing("position")),
+                };
+            };
+            let number = |word: &Word| {
+                word.text.parse::<i32>().map_err(|_| {
+                    error(
+                        word.column,
+                        ParseErrorKind::InvalidNumber(word.text.clone()),
+                    )
+                })
+            };
+            push(Command::Move(number(x)?, number(y)?));
+        }
+        "click" | "mousedown" | "mouseup" => {
+            let word = match arguments {
+                [word] => word,
+                [] => return Err(missing("button")),
+                [_, word, ..] => {
+                    return Err(error(
+                        word.column,
+                        ParseErrorKind::UnexpectedArgument(word.text.clone()),
+                    ))
+                }
+            };
+            let unknown = || {
+                error(
+                    word.column,
+                    ParseErrorKind::UnknownButton(word.text.clone()),
+                )
+            };
+            let invalid_repeat = || {
+                error(
+                    options.repeat_column,
+                    ParseErrorKind::InvalidNumber(options.repeat.to_string()),
+                )
+            };
+            let number = word.text.parse::<u8>().map_err(|_| unknown())?;
+            let button = match number {
+                1 => MouseButton::Left,
+                2 => MouseButton::Middle,
+                3 => MouseButton::Right,
+                8 => MouseButton::X1,
+                9 => MouseButton::X2,
+                4..=7 if name.text == "click" => {
+                    let (vertical, horizontal) = match number {
+                        4 => (1, 0),
+                        5 => (-1, 0),
+                        6 => (0, -1),
+                        _ => (0, 1),
+                    };
+                    let command = i32::try_from(options.repeat)
+                        .ok()
+                        .and_then(|count| Command::scroll(vertical * count, horizontal * count));
+                    push(command.ok_or_else(invalid_repeat)?);
+                    return Ok(());
+                }
+                _ => return Err(unknown()),
+            };
+            match name.text.as_str() {
+                "click" => push(Command::click(button, options.repeat).ok_or_else(invalid_repeat)?),
+                "mousedown" => push(Command::MouseDown(button)),
+                _ => push(Command::MouseUp(button)),
+            }
+        }
+        "sleep" => {
+            let word = arguments.first().ok_or_else(|| missing("seconds"))?;
+            let duration = word
+                .text
+                .parse::<f64>()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .ok_or_else(|| {
+                    error(
+                        word.column,
+                        ParseErrorKind::InvalidDuration(word.text.clone()),
+                    )
+                })?;
+            push(Command::Wait(duration));
+        }
+        _ => {
+            return Err(error(
+                name.column,
+                ParseErrorKind::UnknownCommand(name.text.clone()),
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Options of a command that change the translation
+struct Options {
+    delay: Duration,
+    repeat: u32,
+    /// Column of the `--repeat` value, for errors
+    repeat_column: usize,
+}
+
+/// Separate `--option` words from the arguments
+fn split_options(line_number: usize, words: &[Word]) -> Result<(Options, &[Word]), ParseError> {
+    let mut options = Options {
+        delay: Duration::ZERO,
+        repeat: 1,
+        repeat_column: 0,
+    };
+    let mut index = 0;
+    while let Some(word) = words.get(index) {
+        let Some(option) = word.text.strip_prefix("--") else {
+            break;
+        };
+        index += 1;
+        if option.is_empty() {
+            break;
+        }
+        let takes_value = matches!(option, "delay" | "repeat" | "window" | "screen");
+        if !takes_value {
+            continue;
+        }
+        let value = words.get(index).ok_or(ParseError {
+            line: line_number,
+            column: word.column + word.text.chars().count(),
+            kind: ParseErrorKind::MissingArgument("option value"),
+        })?;
+        index += 1;
+        let invalid = || ParseError {
+            line: line_number,
+            column: value.column,
+            kind: ParseErrorKind::InvalidNumber(value.text.clone()),
+        };
+        match option {
+            "delay" => {
+                options.delay = Duration::from_millis(value.text.parse().map_err(|_| invalid())?)
+            }
+            "repeat" => {
+                options.repeat = value.text.parse().map_err(|_| invalid())?;
+                options.repeat_column = value.column;
+            }
+            _ => {}
+        }
+    }
+    Ok((options, &words[index..]))
+}